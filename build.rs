@@ -1,38 +1,72 @@
 use ethcontract_generate::loaders::TruffleLoader;
 use ethcontract_generate::ContractBuilder;
+use std::{env, path::Path, process};
 
-fn main() {
-    let out_dir = std::env::var("OUT_DIR").unwrap();
-
-    {
-        let dest = std::path::Path::new(&out_dir).join("SugarFungeAsset.rs");
+#[path = "src/artifacts.rs"]
+#[allow(dead_code)]
+mod artifacts;
 
-        let artifact = TruffleLoader::new()
-            .load_from_file("./contracts/SugarFunge/SugarFungeAsset.json")
-            .unwrap();
+use artifacts::{ArtifactFormat, detect, load_artifact};
 
-        for contract in artifact.iter() {
-            ContractBuilder::new()
-                .generate(contract)
-                .unwrap()
-                .write_to_file(&dest)
-                .unwrap();
+/// Generates bindings for contract `name` into `$OUT_DIR/<name>.rs`. A missing artifact fails the
+/// build with the cargo `feature` that needs it, instead of a panic.
+fn generate(out_dir: &str, root: &Path, format: ArtifactFormat, name: &str, truffle_path: &str, feature: Option<&str>) {
+    let artifact = match load_artifact(root, format, name, truffle_path) {
+        Ok(artifact) => artifact,
+        Err(error) => {
+            eprintln!("error: {} ({:?} project at {})", error, format, root.display());
+            match feature {
+                Some(feature) => eprintln!("  it is needed by the `{}` cargo feature: add the artifact, or build without the feature (--no-default-features)", feature),
+                None => eprintln!("  it is needed by the `codegen` cargo feature: add the artifact, or build without the feature to load ABIs at startup"),
+            }
+            process::exit(1);
         }
+    };
+
+    let dest = Path::new(out_dir).join(format!("{}.rs", name));
+
+    let contract = TruffleLoader::new()
+        .load_contract_from_str(&artifact.to_string())
+        .unwrap();
+
+    ContractBuilder::new()
+        .generate(&contract)
+        .unwrap()
+        .write_to_file(&dest)
+        .unwrap();
+}
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    // Without `codegen` the ABIs are loaded at startup instead, so no artifact is needed to build.
+    if env::var("CARGO_FEATURE_CODEGEN").is_err() {
+        return;
     }
 
-    {
-        let dest = std::path::Path::new(&out_dir).join("Wrapped1155Factory.rs");
+    println!("cargo:rerun-if-env-changed=CONTRACTS_DIR");
+    println!("cargo:rerun-if-env-changed=CONTRACTS_FORMAT");
 
-        let artifact = TruffleLoader::new()
-            .load_from_file("./contracts/ErcWrapper/Wrapped1155Factory.json")
-            .unwrap();
+    let root = env::var("CONTRACTS_DIR").unwrap_or_else(|_| "contracts".to_string());
+    let root = Path::new(&root);
 
-        for contract in artifact.iter() {
-            ContractBuilder::new()
-                .generate(contract)
-                .unwrap()
-                .write_to_file(&dest)
-                .unwrap();
-        }
+    println!("cargo:rerun-if-changed={}", root.display());
+
+    let format = match env::var("CONTRACTS_FORMAT") {
+        Ok(format) => format.parse::<ArtifactFormat>().unwrap_or_else(|error| {
+            eprintln!("error: CONTRACTS_FORMAT: {}", error);
+            process::exit(1);
+        }),
+        Err(_) => detect(root),
+    };
+
+    generate(&out_dir, root, format, "SugarFungeAsset", "SugarFunge/SugarFungeAsset.json", None);
+
+    if env::var("CARGO_FEATURE_WRAPPER").is_ok() {
+        generate(&out_dir, root, format, "Wrapped1155Factory", "ErcWrapper/Wrapped1155Factory.json", Some("wrapper"));
+    }
+
+    if env::var("CARGO_FEATURE_RELAYER").is_ok() {
+        generate(&out_dir, root, format, "MinimalForwarder", "Forwarder/MinimalForwarder.json", Some("relayer"));
     }
 }
@@ -0,0 +1,764 @@
+use crate::{error::ApiError, config::{Config, ConfigErrors, non_empty}, chain::ChainConfig, extract::ValidJson, stream::EventHub, auth::Caller, signer::SignerRoute, batch::{BatchIssue, JobState}, asset::{AssetData, AssetMint, AssetTransfer, asset_mint_nft, asset_transfer_nft}, eip712::parse_address};
+use std::{collections::HashMap, fs, io::Write, path::PathBuf, sync::{Mutex, atomic::{AtomicU64, Ordering}}};
+use ethcontract::prelude::*;
+use actix_web::{http::header::CONTENT_TYPE, get, post, rt::spawn, web::{Bytes, BytesMut, Data, Json, Path, Payload, Query}, HttpRequest, HttpResponse, Responder};
+use futures::{stream, StreamExt};
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use log::{error, info};
+
+#[derive(Clone, Debug)]
+pub struct AirdropConfig {
+    pub max_rows: usize,
+    pub concurrency: usize,
+    pub jobs_dir: Option<String>,
+    pub payload_limit: usize,
+}
+
+pub fn init_airdrop(errors: &mut ConfigErrors) -> AirdropConfig {
+    AirdropConfig {
+        max_rows: errors.at_least::<usize>("AIRDROP_MAX_ROWS", 10_000, 1),
+        concurrency: errors.at_least::<usize>("AIRDROP_CONCURRENCY", 1, 1),
+        jobs_dir: non_empty("AIRDROP_JOBS_DIR"),
+        payload_limit: errors.at_least::<usize>("AIRDROP_PAYLOAD_LIMIT", 4_194_304, 1),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AirdropMode {
+    Mint,
+    Transfer
+}
+
+impl AirdropMode {
+    fn route(self) -> SignerRoute {
+        match self {
+            AirdropMode::Mint => SignerRoute::Mint,
+            AirdropMode::Transfer => SignerRoute::Transfer,
+        }
+    }
+}
+
+/// `Sending` rows that are found after a restart become `Unknown` instead of being sent twice.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RowState {
+    Pending,
+    Sending,
+    Submitted,
+    Failed,
+    Unknown
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AirdropEntry {
+    pub address: String,
+    pub id: u64,
+    pub amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AirdropRow {
+    pub address: String,
+    pub id: u64,
+    pub amount: u64,
+    pub state: RowState,
+    #[schema(value_type = Option<String>)]
+    pub tx: Option<H256>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AirdropJob {
+    pub id: u64,
+    pub mode: AirdropMode,
+    pub state: JobState,
+    /// The chain the rows are sent on; jobs saved before chains were configurable run on the default chain.
+    #[serde(default)]
+    pub chain: Option<String>,
+    pub signer: String,
+    pub from: Option<String>,
+    pub data: AssetData,
+    pub total: usize,
+    pub duplicates: usize,
+    pub submitted: usize,
+    pub failed: usize,
+    pub unknown: usize,
+    pub rows: Vec<AirdropRow>,
+}
+
+/// An `AirdropJob` without its rows, as returned when a job starts and when jobs are listed.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AirdropSummary {
+    pub id: u64,
+    pub mode: AirdropMode,
+    pub state: JobState,
+    pub chain: Option<String>,
+    pub total: usize,
+    pub duplicates: usize,
+    pub submitted: usize,
+    pub failed: usize,
+    pub unknown: usize,
+}
+
+impl AirdropJob {
+    fn refresh(&mut self) {
+        self.submitted = self.rows.iter().filter(|row| row.state == RowState::Submitted).count();
+        self.failed = self.rows.iter().filter(|row| row.state == RowState::Failed).count();
+        self.unknown = self.rows.iter().filter(|row| row.state == RowState::Unknown).count();
+    }
+
+    fn summary(&self) -> AirdropSummary {
+        AirdropSummary {
+            id: self.id,
+            mode: self.mode,
+            state: self.state,
+            chain: self.chain.to_owned(),
+            total: self.total,
+            duplicates: self.duplicates,
+            submitted: self.submitted,
+            failed: self.failed,
+            unknown: self.unknown,
+        }
+    }
+}
+
+/// One line of a job's row log, replayed over the job file on startup.
+#[derive(Serialize, Deserialize, Debug)]
+struct RowUpdate {
+    index: usize,
+    state: RowState,
+    tx: Option<H256>,
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AirdropOptions {
+    pub from: Option<String>,
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub symbol: String,
+    #[serde(default)]
+    pub decimals: u64,
+    pub chain: Option<String>,
+    pub signer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct AirdropId {
+    pub id: u64,
+}
+
+/// Airdrop jobs, kept in `AIRDROP_JOBS_DIR` as a job file plus an append-only row log so they resume after a restart.
+pub struct AirdropJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, AirdropJob>>,
+    dir: Option<PathBuf>,
+}
+
+impl AirdropJobs {
+    /// Reads the jobs saved in `AIRDROP_JOBS_DIR`, creating it when missing. A directory that can't be
+    /// read or written is an error; a single unreadable job is skipped.
+    pub fn load(config: &Config) -> Result<Self, String> {
+        AirdropJobs::open(config.airdrop.jobs_dir.as_ref().map(PathBuf::from))
+    }
+
+    fn open(dir: Option<PathBuf>) -> Result<Self, String> {
+        let mut jobs: HashMap<u64, AirdropJob> = HashMap::new();
+
+        if let Some(dir) = &dir {
+            let invalid = |error: std::io::Error| format!("AIRDROP_JOBS_DIR {}: {}", dir.display(), error);
+            let probe = dir.join(".write-check");
+
+            fs::create_dir_all(dir).and_then(|_| fs::write(&probe, "")).and_then(|_| fs::remove_file(&probe)).map_err(invalid)?;
+
+            for entry in fs::read_dir(dir).map_err(invalid)?.flatten() {
+                let path = entry.path();
+
+                if path.extension().map_or(true, |extension| extension != "json") {
+                    continue;
+                }
+
+                let mut job: AirdropJob = match fs::read_to_string(&path).map(|contents| serde_json::from_str(&contents)) {
+                    Ok(Ok(job)) => job,
+                    _ => {
+                        error!("Skipping unreadable airdrop job {}", path.display());
+                        continue;
+                    }
+                };
+
+                if let Ok(log) = fs::read_to_string(path.with_extension("log")) {
+                    for update in log.lines().filter_map(|line| serde_json::from_str::<RowUpdate>(line).ok()) {
+                        if let Some(row) = job.rows.get_mut(update.index) {
+                            row.state = update.state;
+                            row.tx = update.tx;
+                            row.error = update.error;
+                        }
+                    }
+                }
+
+                for row in job.rows.iter_mut().filter(|row| row.state == RowState::Sending) {
+                    row.state = RowState::Unknown;
+                    row.error = Some("service restarted while sending, check the chain before retrying".to_string());
+                }
+
+                job.refresh();
+                jobs.insert(job.id, job);
+            }
+        }
+
+        Ok(AirdropJobs {
+            next_id: AtomicU64::new(jobs.keys().max().copied().unwrap_or(0)),
+            jobs: Mutex::new(jobs),
+            dir,
+        })
+    }
+
+    fn job_path(&self, id: u64, extension: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(format!("{}.{}", id, extension)))
+    }
+
+    fn save(&self, job: &AirdropJob) {
+        if let Some(path) = self.job_path(job.id, "json") {
+            let saved = fs::write(&path, serde_json::to_string(job).unwrap())
+                .and_then(|_| fs::write(path.with_extension("log"), ""));
+
+            if let Err(error) = saved {
+                error!("Saving airdrop job {} failed: {}", job.id, error);
+            }
+        }
+    }
+
+    fn log(&self, id: u64, update: &RowUpdate) {
+        if let Some(path) = self.job_path(id, "log") {
+            let logged = fs::OpenOptions::new().create(true).append(true).open(&path)
+                .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(update).unwrap()));
+
+            if let Err(error) = logged {
+                error!("Logging airdrop job {} failed: {}", id, error);
+            }
+        }
+    }
+
+    fn create(&self, mut job: AirdropJob) -> AirdropJob {
+        job.id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+
+        self.save(&job);
+        self.jobs.lock().unwrap().insert(job.id, job.clone());
+
+        job
+    }
+
+    fn get(&self, id: u64) -> Option<AirdropJob> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    fn update_row(&self, id: u64, update: RowUpdate) {
+        self.log(id, &update);
+
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            let row = &mut job.rows[update.index];
+            row.state = update.state;
+            row.tx = update.tx;
+            row.error = update.error;
+            job.refresh();
+        }
+    }
+
+    fn finish(&self, id: u64, state: JobState) {
+        let job = match self.jobs.lock().unwrap().get_mut(&id) {
+            Some(job) => {
+                job.state = state;
+                job.clone()
+            },
+            None => return
+        };
+
+        self.save(&job);
+    }
+
+    /// Jobs that were still running when the service stopped.
+    pub fn running(&self) -> Vec<u64> {
+        self.jobs.lock().unwrap().values().filter(|job| job.state == JobState::Running).map(|job| job.id).collect()
+    }
+}
+
+/// Issues are reported by entry index, skipping the header and blank lines, the same as for JSON bodies.
+fn parse_csv(text: &str) -> Result<Vec<AirdropEntry>, ApiError> {
+    let mut entries: Vec<AirdropEntry> = Vec::new();
+    let mut issues: Vec<BatchIssue> = Vec::new();
+
+    let is_header = |line: usize, text: &str| line == 0 && text.split(',').next().unwrap_or("").trim().eq_ignore_ascii_case("address");
+
+    let lines = text.lines().enumerate()
+        .filter(|(line, text)| !text.trim().is_empty() && !is_header(*line, text))
+        .map(|(_, text)| text);
+
+    for (index, line) in lines.enumerate() {
+        let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+
+        if columns.len() != 3 {
+            issues.push(BatchIssue::new(index, "row", "expected address,id,amount".to_string()));
+            continue;
+        }
+
+        match (columns[1].parse::<u64>(), columns[2].parse::<u64>()) {
+            (Ok(id), Ok(amount)) => entries.push(AirdropEntry { address: columns[0].to_string(), id, amount }),
+            (Err(_), _) => issues.push(BatchIssue::new(index, "id", format!("{} is not a token id", columns[1]))),
+            (_, Err(_)) => issues.push(BatchIssue::new(index, "amount", format!("{} is not an amount", columns[2]))),
+        }
+    }
+
+    match issues.is_empty() {
+        true => Ok(entries),
+        false => Err(ApiError::InvalidBatch(issues))
+    }
+}
+
+/// Reads the upload up to `AIRDROP_PAYLOAD_LIMIT`, which only applies to the airdrop routes.
+async fn read_body(mut payload: Payload, limit: usize) -> Result<Bytes, ApiError> {
+    let mut body = BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|error| ApiError::InvalidRequest(error.to_string()))?;
+
+        if body.len() + chunk.len() > limit {
+            return Err(ApiError::PayloadTooLarge(limit));
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body.freeze())
+}
+
+/// Reads `text/csv` (`address,id,amount` per line, optional header) or a JSON array of rows.
+fn parse_entries(req: &HttpRequest, body: &Bytes) -> Result<Vec<AirdropEntry>, ApiError> {
+    let content_type = req.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or("");
+
+    if content_type.starts_with("text/csv") {
+        let text = std::str::from_utf8(body).map_err(|_| ApiError::InvalidRequest("CSV body is not UTF-8".to_string()))?;
+        return parse_csv(text);
+    }
+
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Validates the rows and drops exact duplicates; the same address and id with different amounts is rejected.
+fn prepare_rows(max_rows: usize, entries: Vec<AirdropEntry>) -> Result<(Vec<AirdropRow>, usize), ApiError> {
+    if entries.is_empty() {
+        return Err(ApiError::InvalidBatch([BatchIssue::new(0, "row", "airdrop has no rows".to_string())].to_vec()));
+    }
+    if entries.len() > max_rows {
+        return Err(ApiError::InvalidBatch([BatchIssue::new(max_rows, "row", format!("airdrop is larger than the maximum of {} rows", max_rows))].to_vec()));
+    }
+
+    let mut rows: Vec<AirdropRow> = Vec::new();
+    let mut issues: Vec<BatchIssue> = Vec::new();
+    let mut seen: HashMap<(H160, u64), (usize, u64)> = HashMap::new();
+    let mut duplicates = 0;
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let address = match parse_address("address", &entry.address) {
+            Ok(address) => address,
+            Err(_) => {
+                issues.push(BatchIssue::new(index, "address", format!("{} is not a valid address", entry.address)));
+                continue;
+            }
+        };
+
+        if entry.amount == 0 {
+            issues.push(BatchIssue::new(index, "amount", "amount must be greater than zero".to_string()));
+            continue;
+        }
+
+        match seen.get(&(address, entry.id)) {
+            Some((_, amount)) if *amount == entry.amount => duplicates += 1,
+            Some((first, _)) => issues.push(BatchIssue::new(index, "amount", format!("conflicts with the amount at index {}", first))),
+            None => {
+                seen.insert((address, entry.id), (index, entry.amount));
+                rows.push(AirdropRow {
+                    address: format!("0x{:x}", address),
+                    id: entry.id,
+                    amount: entry.amount,
+                    state: RowState::Pending,
+                    tx: None,
+                    error: None,
+                });
+            }
+        }
+    }
+
+    match issues.is_empty() {
+        true => Ok((rows, duplicates)),
+        false => Err(ApiError::InvalidBatch(issues))
+    }
+}
+
+/// Sends one row, checking the signer again first so `BALANCE_REFUSE_WRITES` applies for the whole job.
+async fn send_row(config: &Config, jobs: &AirdropJobs, hub: &EventHub, chain: &ChainConfig, job: &AirdropJob, index: usize) {
+    let row = &job.rows[index];
+
+    let signer = match config.accounts.select(job.mode.route(), &Some(job.signer.to_owned()), &None, chain) {
+        Ok(signer) => signer,
+        Err(error) => return jobs.update_row(job.id, RowUpdate { index, state: RowState::Failed, tx: None, error: Some(error.to_string()) }),
+    };
+
+    jobs.update_row(job.id, RowUpdate { index, state: RowState::Sending, tx: None, error: None });
+
+    let result = match job.mode {
+        AirdropMode::Mint => asset_mint_nft(config, signer, &AssetMint {
+            account: row.address.to_owned(),
+            amount: row.amount,
+            id: row.id,
+            data: job.data.clone(),
+            chain: job.chain.to_owned(),
+            signer: None,
+        }).await,
+        AirdropMode::Transfer => asset_transfer_nft(config, signer, &AssetTransfer {
+            from: job.from.to_owned().unwrap_or_default(),
+            to: row.address.to_owned(),
+            amount: row.amount,
+            id: row.id,
+            data: job.data.clone(),
+            chain: job.chain.to_owned(),
+            signer: None,
+        }).await,
+    };
+
+    match result {
+        Ok(tx) => {
+            hub.track(tx, &chain.name, &format!("airdrop_{}", job.id), [row.address.to_owned()].to_vec(), [row.id].to_vec());
+            jobs.update_row(job.id, RowUpdate { index, state: RowState::Submitted, tx: Some(tx), error: None });
+        },
+        Err(error) => jobs.update_row(job.id, RowUpdate { index, state: RowState::Failed, tx: None, error: Some(error.to_string()) }),
+    }
+}
+
+/// Sends every pending row of the job, at most `AIRDROP_CONCURRENCY` at a time. The job ends `failed` when any row failed.
+pub async fn run_airdrop(config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, id: u64) {
+    let job = match jobs.get(id) {
+        Some(job) => job,
+        None => return
+    };
+
+    if let Err(error) = config.accounts.get(&job.signer) {
+        error!("Airdrop job {} can't run: {}", id, error);
+        jobs.finish(id, JobState::Failed);
+        return;
+    }
+
+    let chain = match config.chains.select(&job.chain) {
+        Ok(chain) => chain,
+        Err(error) => {
+            error!("Airdrop job {} can't run: {}", id, error);
+            jobs.finish(id, JobState::Failed);
+            return;
+        }
+    };
+
+    let pending: Vec<usize> = job.rows.iter().enumerate()
+        .filter(|(_, row)| row.state == RowState::Pending)
+        .map(|(index, _)| index)
+        .collect();
+
+    info!("Airdrop job {}: sending {} of {} rows", id, pending.len(), job.total);
+
+    let (config, jobs, hub, job) = (&config, &jobs, &hub, &job);
+
+    stream::iter(pending)
+        .for_each_concurrent(config.airdrop.concurrency, |index| send_row(config, jobs, hub, chain, job, index))
+        .await;
+
+    match jobs.get(id) {
+        Some(job) if job.failed > 0 => jobs.finish(id, JobState::Failed),
+        _ => jobs.finish(id, JobState::Completed),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn create_airdrop(req: HttpRequest, payload: Payload, options: AirdropOptions, mode: AirdropMode, config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<HttpResponse, ApiError> {
+    options.validate().map_err(ApiError::ValidationError)?;
+
+    let route = mode.route();
+
+    let from = match (mode, &options.from) {
+        (AirdropMode::Transfer, None) => return Err(ApiError::InvalidRequest("from is required for transfer airdrops".to_string())),
+        (AirdropMode::Transfer, Some(from)) => Some(format!("0x{:x}", parse_address("from", from)?)),
+        (AirdropMode::Mint, _) => None,
+    };
+
+    let chain = config.chains.select(&options.chain)?;
+
+    config.accounts.select(route, &options.signer, &caller, chain)?;
+
+    let signer = match &options.signer {
+        Some(name) => name.to_lowercase(),
+        None => config.accounts.route_account(route).to_string(),
+    };
+
+    let body = read_body(payload, config.airdrop.payload_limit).await?;
+
+    let (rows, duplicates) = prepare_rows(config.airdrop.max_rows, parse_entries(&req, &body)?)?;
+
+    let mut job = AirdropJob {
+        id: 0,
+        mode,
+        state: JobState::Running,
+        chain: Some(chain.name.to_owned()),
+        signer,
+        from,
+        data: AssetData { name: options.name, symbol: options.symbol, decimals: options.decimals },
+        total: rows.len(),
+        duplicates,
+        submitted: 0,
+        failed: 0,
+        unknown: 0,
+        rows,
+    };
+    job.refresh();
+
+    let job = jobs.create(job);
+
+    spawn(run_airdrop(config, jobs, hub, job.id));
+
+    Ok(HttpResponse::Accepted().json(job.summary()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/create_mint_airdrop",
+    tag = "legacy",
+    request_body(content = Vec<AirdropEntry>, description = "JSON array of entries, or the same columns as a `text/csv` body with an `address,id,amount` header"),
+    params(
+        AirdropOptions
+    ),
+    responses(
+        (status = 202, description = "Airdrop job started", body = AirdropSummary),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("create_mint_airdrop")]
+async fn create_mint_airdrop(req: HttpRequest, payload: Payload, options: Query<AirdropOptions>, config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create_airdrop(req, payload, options.into_inner(), AirdropMode::Mint, config, jobs, hub, caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/create_transfer_airdrop",
+    tag = "legacy",
+    request_body(content = Vec<AirdropEntry>, description = "JSON array of entries, or the same columns as a `text/csv` body with an `address,id,amount` header"),
+    params(
+        AirdropOptions
+    ),
+    responses(
+        (status = 202, description = "Airdrop job started", body = AirdropSummary),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("create_transfer_airdrop")]
+async fn create_transfer_airdrop(req: HttpRequest, payload: Payload, options: Query<AirdropOptions>, config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create_airdrop(req, payload, options.into_inner(), AirdropMode::Transfer, config, jobs, hub, caller).await
+}
+
+fn find_airdrop(jobs: &AirdropJobs, id: u64) -> Result<Json<AirdropJob>, ApiError> {
+    match jobs.get(id) {
+        Some(job) => Ok(Json(job)),
+        None => Err(ApiError::JobNotFound(id)),
+    }
+}
+
+fn list(jobs: &AirdropJobs) -> Json<Vec<AirdropSummary>> {
+    let mut summaries: Vec<(u64, AirdropSummary)> = jobs.jobs.lock().unwrap().values().map(|job| (job.id, job.summary())).collect();
+
+    summaries.sort_by_key(|(id, _)| *id);
+
+    Json(summaries.into_iter().map(|(_, summary)| summary).collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_airdrop",
+    tag = "legacy",
+    request_body = AirdropId,
+    responses(
+        (status = 200, description = "Airdrop job with every row", body = AirdropJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("get_airdrop")]
+async fn get_airdrop(req_data: ValidJson<AirdropId>, jobs: Data<AirdropJobs>) -> Result<impl Responder, ApiError> {
+    find_airdrop(&jobs, req_data.id)
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_airdrops",
+    tag = "legacy",
+    responses(
+        (status = 200, description = "All airdrop jobs", body = [AirdropSummary]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("get_airdrops")]
+async fn get_airdrops(jobs: Data<AirdropJobs>) -> Result<impl Responder, ApiError> {
+    Ok(list(&jobs))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/airdrops/mint",
+    tag = "airdrops",
+    request_body(content = Vec<AirdropEntry>, description = "JSON array of entries, or the same columns as a `text/csv` body with an `address,id,amount` header"),
+    params(
+        AirdropOptions
+    ),
+    responses(
+        (status = 202, description = "Airdrop job started", body = AirdropSummary),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/airdrops/mint")]
+async fn start_mint_airdrop(req: HttpRequest, payload: Payload, options: Query<AirdropOptions>, config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create_airdrop(req, payload, options.into_inner(), AirdropMode::Mint, config, jobs, hub, caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/airdrops/transfer",
+    tag = "airdrops",
+    request_body(content = Vec<AirdropEntry>, description = "JSON array of entries, or the same columns as a `text/csv` body with an `address,id,amount` header"),
+    params(
+        AirdropOptions
+    ),
+    responses(
+        (status = 202, description = "Airdrop job started", body = AirdropSummary),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/airdrops/transfer")]
+async fn start_transfer_airdrop(req: HttpRequest, payload: Payload, options: Query<AirdropOptions>, config: Data<Config>, jobs: Data<AirdropJobs>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create_airdrop(req, payload, options.into_inner(), AirdropMode::Transfer, config, jobs, hub, caller).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/airdrops",
+    tag = "airdrops",
+    responses(
+        (status = 200, description = "All airdrop jobs", body = [AirdropSummary]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/airdrops")]
+async fn list_airdrops(jobs: Data<AirdropJobs>) -> Result<impl Responder, ApiError> {
+    Ok(list(&jobs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/airdrops/{id}",
+    tag = "airdrops",
+    params(
+        ("id" = u64, Path, description = "Job id")
+    ),
+    responses(
+        (status = 200, description = "Airdrop job with every row", body = AirdropJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/airdrops/{id}")]
+async fn get_airdrop_job(id: Path<u64>, jobs: Data<AirdropJobs>) -> Result<impl Responder, ApiError> {
+    find_airdrop(&jobs, id.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "0x00000000000000000000000000000000000000a1";
+    const BOB: &str = "0x00000000000000000000000000000000000000b2";
+
+    fn entry(address: &str, id: u64, amount: u64) -> AirdropEntry {
+        AirdropEntry { address: address.to_string(), id, amount }
+    }
+
+    fn issues(result: Result<impl std::fmt::Debug, ApiError>) -> Vec<(usize, &'static str)> {
+        match result {
+            Err(ApiError::InvalidBatch(issues)) => issues.into_iter().map(|issue| (issue.index, issue.field)).collect(),
+            other => panic!("expected InvalidBatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn csv_skips_the_header_and_blank_lines() {
+        let entries = parse_csv(&format!("Address, id, amount\n{},1,5\n\n{}, 2, 7\n", ALICE, BOB)).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[1].address.as_str(), entries[1].id, entries[1].amount), (BOB, 2, 7));
+    }
+
+    #[test]
+    fn csv_issues_are_indexed_by_entry() {
+        let csv = format!("address,id,amount\n{},1,5\n\n{},x,5\n{},1\naddress,id,amount\n", ALICE, BOB, BOB);
+
+        assert_eq!(issues(parse_csv(&csv)), [(1, "id"), (2, "row"), (3, "id")]);
+    }
+
+    #[test]
+    fn exact_duplicates_are_dropped_and_conflicts_rejected() {
+        let (rows, duplicates) = prepare_rows(10, [entry(ALICE, 1, 5), entry(BOB, 1, 5), entry(ALICE, 1, 5)].to_vec()).unwrap();
+
+        assert_eq!((rows.len(), duplicates), (2, 1));
+
+        let conflicting = prepare_rows(10, [entry(ALICE, 1, 5), entry(ALICE, 1, 6), entry("0x12", 1, 5), entry(BOB, 1, 0)].to_vec());
+
+        assert_eq!(issues(conflicting), [(1, "amount"), (2, "address"), (3, "amount")]);
+    }
+
+    #[test]
+    fn too_many_rows_are_rejected() {
+        assert_eq!(issues(prepare_rows(1, [entry(ALICE, 1, 5), entry(BOB, 1, 5)].to_vec())), [(1, "row")]);
+        assert_eq!(issues(prepare_rows(1, [].to_vec())), [(0, "row")]);
+    }
+
+    #[test]
+    fn rows_being_sent_at_a_restart_become_unknown() {
+        let dir = std::env::temp_dir().join(format!("airdrop-jobs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (rows, duplicates) = prepare_rows(10, [entry(ALICE, 1, 5), entry(BOB, 1, 5)].to_vec()).unwrap();
+
+        let jobs = AirdropJobs::open(Some(dir.clone())).unwrap();
+        let job = jobs.create(AirdropJob {
+            id: 0,
+            mode: AirdropMode::Mint,
+            state: JobState::Running,
+            chain: None,
+            signer: "default".to_string(),
+            from: None,
+            data: AssetData { name: "Sugar".to_string(), symbol: "SGR".to_string(), decimals: 0 },
+            total: rows.len(),
+            duplicates,
+            submitted: 0,
+            failed: 0,
+            unknown: 0,
+            rows,
+        });
+        jobs.update_row(job.id, RowUpdate { index: 0, state: RowState::Submitted, tx: Some(H256::zero()), error: None });
+        jobs.update_row(job.id, RowUpdate { index: 1, state: RowState::Sending, tx: None, error: None });
+
+        let restarted = AirdropJobs::open(Some(dir.clone())).unwrap();
+        let job = restarted.get(job.id).unwrap();
+
+        assert_eq!(restarted.running(), [job.id]);
+        assert_eq!(job.rows[0].state, RowState::Submitted);
+        assert_eq!(job.rows[1].state, RowState::Unknown);
+        assert_eq!((job.submitted, job.unknown), (1, 1));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+//! Reads contract artifacts from Truffle, Hardhat or Foundry projects. Shared by `build.rs` and the
+//! runtime ABI loader, it reduces each artifact to a Truffle-style `{contractName, abi, networks}`
+//! document, with the deployment addresses taken from the tool's own deployment files.
+
+use std::{fs, path::{Path, PathBuf}, str::FromStr};
+use serde_json::{json, Map, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArtifactFormat {
+    Truffle,
+    Hardhat,
+    Foundry,
+}
+
+impl FromStr for ArtifactFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "truffle" => Ok(ArtifactFormat::Truffle),
+            "hardhat" => Ok(ArtifactFormat::Hardhat),
+            "foundry" => Ok(ArtifactFormat::Foundry),
+            _ => Err(format!("unknown artifact format \"{}\", expected truffle, hardhat or foundry", format)),
+        }
+    }
+}
+
+/// A Hardhat project has `artifacts/`, a Foundry project `out/`; anything else is read as Truffle.
+pub fn detect(root: &Path) -> ArtifactFormat {
+    if root.join("artifacts").is_dir() {
+        ArtifactFormat::Hardhat
+    } else if root.join("out").is_dir() {
+        ArtifactFormat::Foundry
+    } else {
+        ArtifactFormat::Truffle
+    }
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    let contents = fs::read_to_string(path).map_err(|error| format!("reading {} failed: {}", path.display(), error))?;
+
+    serde_json::from_str(&contents).map_err(|error| format!("{} is invalid: {}", path.display(), error))
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// `<name>.json` anywhere under `artifacts/`, skipping `build-info` and the `.dbg.json` debug files.
+fn find_hardhat_artifact(dir: &Path, name: &str) -> Option<PathBuf> {
+    let file = format!("{}.json", name);
+
+    for path in entries(dir) {
+        if path.is_dir() && !path.ends_with("build-info") {
+            if let Some(found) = find_hardhat_artifact(&path, name) {
+                return Some(found);
+            }
+        } else if path.file_name().map_or(false, |file_name| file_name == file.as_str()) {
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+/// hardhat-deploy writes `deployments/<network>/<name>.json` next to a `.chainId` file.
+fn hardhat_networks(root: &Path, name: &str) -> Map<String, Value> {
+    let mut networks = Map::new();
+
+    for network in entries(&root.join("deployments")) {
+        let chain_id = match fs::read_to_string(network.join(".chainId")) {
+            Ok(chain_id) => chain_id.trim().to_string(),
+            Err(_) => continue,
+        };
+
+        if let Ok(deployment) = read_json(&network.join(format!("{}.json", name))) {
+            if let Some(address) = deployment.get("address") {
+                networks.insert(chain_id, json!({ "address": address }));
+            }
+        }
+    }
+
+    networks
+}
+
+/// `forge script --broadcast` writes `broadcast/<script>/<chain id>/run-latest.json`; the last
+/// contract creation named `name` on each chain is its deployment.
+fn foundry_networks(root: &Path, name: &str) -> Map<String, Value> {
+    let mut networks = Map::new();
+
+    for script in entries(&root.join("broadcast")) {
+        for chain in entries(&script) {
+            let chain_id = match chain.file_name().and_then(|chain_id| chain_id.to_str()) {
+                Some(chain_id) if chain_id.parse::<u64>().is_ok() => chain_id.to_string(),
+                _ => continue,
+            };
+
+            let run = match read_json(&chain.join("run-latest.json")) {
+                Ok(run) => run,
+                Err(_) => continue,
+            };
+
+            let transactions = run.get("transactions").and_then(|transactions| transactions.as_array()).cloned().unwrap_or_default();
+
+            for transaction in transactions {
+                let created = matches!(transaction.get("transactionType").and_then(|kind| kind.as_str()), Some("CREATE") | Some("CREATE2"));
+
+                if created && transaction.get("contractName").and_then(|contract| contract.as_str()) == Some(name) {
+                    if let Some(address) = transaction.get("contractAddress") {
+                        networks.insert(chain_id.to_owned(), json!({ "address": address }));
+                    }
+                }
+            }
+        }
+    }
+
+    networks
+}
+
+/// Keeps the ABI and the deployments of any single artifact file, or takes a plain ABI list as is.
+pub fn normalize(name: &str, artifact: Value, networks: Option<Map<String, Value>>) -> Result<Value, String> {
+    let (abi, artifact_networks) = match artifact {
+        Value::Array(abi) => (Value::Array(abi), Map::new()),
+        Value::Object(mut artifact) => {
+            let abi = artifact.remove("abi").ok_or_else(|| format!("the {} artifact has no abi", name))?;
+            let networks = match artifact.remove("networks") {
+                Some(Value::Object(networks)) => networks,
+                _ => Map::new(),
+            };
+
+            (abi, networks)
+        },
+        _ => return Err(format!("the {} artifact must be an object or an ABI list", name)),
+    };
+
+    Ok(json!({
+        "contractName": name,
+        "abi": abi,
+        "networks": networks.unwrap_or(artifact_networks)
+    }))
+}
+
+/// The artifact of contract `name` in the project at `root`. Truffle artifacts are found at
+/// `truffle_path` below `root`, Hardhat ones under `artifacts/` and Foundry ones at `out/<name>.sol/`.
+pub fn load_artifact(root: &Path, format: ArtifactFormat, name: &str, truffle_path: &str) -> Result<Value, String> {
+    let (path, networks) = match format {
+        ArtifactFormat::Truffle => (Some(root.join(truffle_path)), None),
+        ArtifactFormat::Hardhat => (find_hardhat_artifact(&root.join("artifacts"), name), Some(hardhat_networks(root, name))),
+        ArtifactFormat::Foundry => (Some(root.join("out").join(format!("{}.sol", name)).join(format!("{}.json", name))), Some(foundry_networks(root, name))),
+    };
+
+    match path {
+        Some(path) if path.is_file() => normalize(name, read_json(&path)?, networks),
+        Some(path) => Err(format!("the {} artifact {} was not found", name, path.display())),
+        None => Err(format!("the {} artifact was not found under {}", name, root.join("artifacts").display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(project: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/artifacts").join(project)
+    }
+
+    fn address(artifact: &Value, chain_id: &str) -> Option<String> {
+        artifact["networks"][chain_id]["address"].as_str().map(str::to_string)
+    }
+
+    #[test]
+    fn the_format_is_detected_from_the_project_layout() {
+        assert_eq!(detect(&fixture("hardhat")), ArtifactFormat::Hardhat);
+        assert_eq!(detect(&fixture("foundry")), ArtifactFormat::Foundry);
+        assert_eq!(detect(&fixture("truffle")), ArtifactFormat::Truffle);
+    }
+
+    #[test]
+    fn hardhat_deployments_are_keyed_by_their_chain_id_file() {
+        let artifact = load_artifact(&fixture("hardhat"), ArtifactFormat::Hardhat, "Token", "").unwrap();
+
+        assert_eq!(artifact["abi"][0]["name"], "uri");
+        assert_eq!(address(&artifact, "5").as_deref(), Some("0x5FbDB2315678afecb367f032d93F642f64180aa3"));
+        assert_eq!(artifact["networks"].as_object().unwrap().len(), 1, "a network without .chainId is skipped");
+    }
+
+    #[test]
+    fn forge_broadcasts_give_the_last_creation_per_chain() {
+        let artifact = load_artifact(&fixture("foundry"), ArtifactFormat::Foundry, "Token", "").unwrap();
+
+        assert_eq!(artifact["abi"][0]["name"], "uri");
+        assert_eq!(address(&artifact, "137").as_deref(), Some("0xCf7Ed3AccA5a467e9e704C703E8D87F634fB0Fc9"));
+        assert_eq!(artifact["networks"].as_object().unwrap().len(), 1, "dry runs are skipped");
+    }
+
+    #[test]
+    fn truffle_artifacts_keep_their_networks() {
+        let artifact = load_artifact(&fixture("truffle"), ArtifactFormat::Truffle, "Token", "build/contracts/Token.json").unwrap();
+
+        assert_eq!(address(&artifact, "1337").as_deref(), Some("0x8464135c8F25Da09e49BC8782676a84730C318bC"));
+    }
+
+    #[test]
+    fn a_missing_artifact_is_an_error() {
+        assert!(load_artifact(&fixture("hardhat"), ArtifactFormat::Hardhat, "Wrapper", "").is_err());
+        assert!(load_artifact(&fixture("foundry"), ArtifactFormat::Foundry, "Wrapper", "").is_err());
+    }
+}
@@ -1,52 +1,89 @@
-use crate::{error::ApiError, config::Config};
-use std::{env, str::FromStr};
+use crate::{error::ApiError, config::Config, chain::ChainConfig, contracts::asset_contract, extract::{ValidJson, validate_address}, eip712::parse_address, batch::{BatchJobs, send_batch}, stream::EventHub, auth::Caller, signer::{Signer, SignerRoute}};
+use std::ops::Range;
 use ethcontract::{prelude::*, web3::ethabi::{Token, encode}};
-use actix_web::{post, web::{Data, Json}, Responder};
+use actix_web::{post, web::{Data, Json}, HttpResponse, Responder};
 use serde::{Serialize, Deserialize};
-use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
-include!(concat!(env!("OUT_DIR"), "/SugarFungeAsset.rs"));
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AssetData {
+    #[validate(length(min = 1))]
     pub name: String,
+    #[validate(length(min = 1))]
     pub symbol: String,
     pub decimals: u64
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct AssetMint {
-    account: String,
-    amount: u64,
-    id: u64,
-    data: AssetData,
+    #[validate(custom = "validate_address")]
+    pub account: String,
+    pub amount: u64,
+    pub id: u64,
+    #[validate]
+    pub data: AssetData,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct AssetTransfer {
+    #[validate(custom = "validate_address")]
     pub from: String,
+    #[validate(custom = "validate_address")]
     pub to: String,
     pub amount: u64,
     pub id: u64,
+    #[validate]
     pub data: AssetData,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct AssetBatchTransfer {
+    #[validate(custom = "validate_address")]
     pub from: String,
+    #[validate(custom = "validate_address")]
     pub to: String,
     pub amounts: Vec<u64>,
+    #[validate(length(min = 1))]
     pub ids: Vec<u64>,
+    #[validate]
     pub data: Vec<AssetData>,
+    #[serde(default)]
+    pub allow_duplicate_ids: bool,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-pub fn get_web3(config: &Config) -> Result<Web3<Http>, ApiError> {
+impl AssetBatchTransfer {
+    /// The entries in `range` as a batch of their own.
+    pub fn chunk(&self, range: Range<usize>) -> AssetBatchTransfer {
+        AssetBatchTransfer {
+            from: self.from.to_owned(),
+            to: self.to.to_owned(),
+            amounts: self.amounts[range.clone()].to_vec(),
+            ids: self.ids[range.clone()].to_vec(),
+            data: self.data[range].to_vec(),
+            allow_duplicate_ids: self.allow_duplicate_ids,
+            chain: self.chain.to_owned(),
+            signer: None,
+        }
+    }
+}
 
-    let infura_url = {
-        format!("https://ropsten.infura.io/v3/{}", config.project_id)
-    };
+pub fn get_web3(chain: &ChainConfig) -> Result<Web3<Http>, ApiError> {
 
-    match Http::new(&infura_url) {
+    match Http::new(&chain.rpc_url) {
         Ok(http) => Ok(Web3::new(http)),
         Err(_) => Err(ApiError::TransportError),
     }
@@ -86,103 +123,208 @@ pub fn get_batch_asset_data(data: Vec<AssetData>) -> Bytes<Vec<u8>> {
     ethcontract::Bytes(encode(&tokens))
 }
 
-pub async fn asset_mint_nft(config: &Config, mint: &AssetMint) -> Result<impl Responder, ApiError> {
+/// Body of every endpoint that answers with a submitted transaction.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TxResponse {
+    #[schema(example = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060")]
+    pub tx: String,
+}
+
+pub fn tx_response(tx: H256) -> Json<TxResponse> {
+    Json(TxResponse {
+        tx: format!("0x{:x}", tx)
+    })
+}
 
-    let account = {
-        let key: PrivateKey = config.private_key.to_owned();
-        Account::Offline(key, Some(config.chain_id))
-    };
+pub async fn asset_mint_nft(config: &Config, signer: &Signer, mint: &AssetMint) -> Result<H256, ApiError> {
 
-    let web3 = get_web3(config)?;
+    let chain = config.chains.select(&mint.chain)?;
 
-    let mut contract =  SugarFungeAsset::deployed(&web3).await?;
+    let web3 = get_web3(chain)?;
 
-    contract.defaults_mut().from = Some(account);
+    let contract = asset_contract(chain, &web3).await?;
 
-    let result = contract.mint(
-        H160::from_str(&mint.account).unwrap(), 
+    let method = contract.mint(
+        parse_address("account", &mint.account)?, 
         mint.id.into(), 
         mint.amount.into(), 
-        get_asset_data(mint.data.name.to_owned(), mint.data.symbol.to_owned(), mint.data.decimals))
-        .send()
-        .await?;
+        get_asset_data(mint.data.name.to_owned(), mint.data.symbol.to_owned(), mint.data.decimals));
 
-    Ok(Json(json!({
-        "tx": format!("0x{:x}", result.hash())
-    })))
+    signer.send(&web3, chain.id, method).await
 }
 
-pub async fn asset_transfer_nft(config: &Config, transfer: &AssetTransfer) -> Result<impl Responder, ApiError> {
+pub async fn asset_transfer_nft(config: &Config, signer: &Signer, transfer: &AssetTransfer) -> Result<H256, ApiError> {
 
-    let account = {
-        let key: PrivateKey = config.private_key.to_owned();
-        Account::Offline(key, Some(config.chain_id))
-    };
+    let chain = config.chains.select(&transfer.chain)?;
 
-    let web3 = get_web3(config)?;
+    let web3 = get_web3(chain)?;
 
-    let mut contract = SugarFungeAsset::deployed(&web3).await?;
+    let contract = asset_contract(chain, &web3).await?;
 
-    contract.defaults_mut().from = Some(account);
-
-    let result = contract.safe_transfer_from (
-        H160::from_str(&transfer.from).unwrap(), 
-        H160::from_str(&transfer.to).unwrap(), 
+    let method = contract.safe_transfer_from (
+        parse_address("from", &transfer.from)?, 
+        parse_address("to", &transfer.to)?, 
         transfer.id.into(), 
         transfer.amount.into(),
-        get_asset_data(transfer.data.name.to_owned(), transfer.data.symbol.to_owned(), transfer.data.decimals))
-        .send()
-        .await?;
+        get_asset_data(transfer.data.name.to_owned(), transfer.data.symbol.to_owned(), transfer.data.decimals));
 
-    Ok(Json(json!({
-        "tx": format!("0x{:x}", result.hash())
-    })))
+    signer.send(&web3, chain.id, method).await
 }
 
-pub async fn asset_batch_transfer_nft(config: &Config, transfer: &AssetBatchTransfer) -> Result<impl Responder, ApiError> {
+pub async fn asset_batch_transfer_nft(config: &Config, signer: &Signer, transfer: &AssetBatchTransfer) -> Result<H256, ApiError> {
+
+    let chain = config.chains.select(&transfer.chain)?;
 
-    let account = {
-        let key: PrivateKey = config.private_key.to_owned();
-        Account::Offline(key, Some(config.chain_id))
-    };
+    let web3 = get_web3(chain)?;
 
-    let web3 = get_web3(config)?;
+    let contract = asset_contract(chain, &web3).await?;
 
-    let mut contract = SugarFungeAsset::deployed(&web3).await?;
+    let method = contract.safe_batch_transfer_from (
+        parse_address("from", &transfer.from)?, 
+        parse_address("to", &transfer.to)?, 
+        transfer.ids.iter().map(|x| x.to_owned().into()).collect(), 
+        transfer.amounts.iter().map(|x| x.to_owned().into()).collect(),
+        get_batch_asset_data(transfer.data.to_vec()));
+
+    signer.send(&web3, chain.id, method).await
+}
+
+/// Gas `asset_batch_transfer_nft` would use for `transfer`, so oversized batches can be split.
+pub async fn asset_batch_transfer_gas(config: &Config, signer: &Signer, transfer: &AssetBatchTransfer) -> Result<U256, ApiError> {
+
+    let chain = config.chains.select(&transfer.chain)?;
 
-    contract.defaults_mut().from = Some(account);
+    let web3 = get_web3(chain)?;
 
-    let result = contract.safe_batch_transfer_from (
-        H160::from_str(&transfer.from).unwrap(), 
-        H160::from_str(&transfer.to).unwrap(), 
+    let contract = asset_contract(chain, &web3).await?;
+
+    let method = contract.safe_batch_transfer_from (
+        parse_address("from", &transfer.from)?, 
+        parse_address("to", &transfer.to)?, 
         transfer.ids.iter().map(|x| x.to_owned().into()).collect(), 
         transfer.amounts.iter().map(|x| x.to_owned().into()).collect(),
-        get_batch_asset_data(transfer.data.to_vec()))
-        .send()
-        .await?;
+        get_batch_asset_data(transfer.data.to_vec()));
 
-    Ok(Json(json!({
-        "tx": format!("0x{:x}", result.hash())
-    })))
+    signer.estimate_gas(&web3, &method).await
 }
 
-#[post("mint_nft")]
-async fn mint_nft(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: AssetMint = serde_json::from_str(&req_body)?;
+async fn send_mint(mint: &AssetMint, config: &Config, hub: &EventHub, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&mint.chain)?;
+    let signer = config.accounts.select(SignerRoute::Mint, &mint.signer, caller, chain)?;
+
+    let tx = asset_mint_nft(config, signer, mint).await?;
 
-    asset_mint_nft(&config, &req_data).await
+    hub.track(tx, &chain.name, "mint_nft", [mint.account.to_owned()].to_vec(), [mint.id].to_vec());
+
+    Ok(tx_response(tx))
 }
 
-#[post("transfer_nft")]
-async fn transfer_nft(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: AssetTransfer = serde_json::from_str(&req_body)?;
+async fn send_transfer(transfer: &AssetTransfer, config: &Config, hub: &EventHub, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&transfer.chain)?;
+    let signer = config.accounts.select(SignerRoute::Transfer, &transfer.signer, caller, chain)?;
 
-    asset_transfer_nft(&config, &req_data).await
+    let tx = asset_transfer_nft(config, signer, transfer).await?;
+
+    hub.track(tx, &chain.name, "transfer_nft", [transfer.from.to_owned(), transfer.to.to_owned()].to_vec(), [transfer.id].to_vec());
+
+    Ok(tx_response(tx))
+}
+
+async fn send_batch_transfer(transfer: AssetBatchTransfer, config: &Data<Config>, hub: &Data<EventHub>, jobs: &Data<BatchJobs>, caller: &Option<Caller>) -> Result<HttpResponse, ApiError> {
+    let chain = config.chains.select(&transfer.chain)?;
+    let signer = config.accounts.select(SignerRoute::Transfer, &transfer.signer, caller, chain)?;
+
+    send_batch(config, jobs, hub, chain, signer, transfer, "batch_transfer_nft").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/mint_nft",
+    tag = "legacy",
+    request_body = AssetMint,
+    responses(
+        (status = 200, description = "Mint transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("mint_nft")]
+async fn mint_nft(req_data: ValidJson<AssetMint>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_mint(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/transfer_nft",
+    tag = "legacy",
+    request_body = AssetTransfer,
+    responses(
+        (status = 200, description = "Transfer transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("transfer_nft")]
+async fn transfer_nft(req_data: ValidJson<AssetTransfer>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_transfer(&req_data, &config, &hub, &caller).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/batch_transfer_nft",
+    tag = "legacy",
+    request_body = AssetBatchTransfer,
+    responses(
+        (status = 200, description = "Sent as one transaction", body = TxResponse),
+        (status = 202, description = "Too large for one transaction, sent as a chunked job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("batch_transfer_nft")]
-async fn batch_transfer_nft(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: AssetBatchTransfer = serde_json::from_str(&req_body)?;
+async fn batch_transfer_nft(req_data: ValidJson<AssetBatchTransfer>, config: Data<Config>, hub: Data<EventHub>, jobs: Data<BatchJobs>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_batch_transfer(req_data.into_inner(), &config, &hub, &jobs, &caller).await
+}
 
-    asset_batch_transfer_nft(&config, &req_data).await
+#[utoipa::path(
+    post,
+    path = "/v1/tokens/mint",
+    tag = "tokens",
+    request_body = AssetMint,
+    responses(
+        (status = 200, description = "Mint transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/tokens/mint")]
+async fn mint_token(req_data: ValidJson<AssetMint>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_mint(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tokens/transfer",
+    tag = "tokens",
+    request_body = AssetTransfer,
+    responses(
+        (status = 200, description = "Transfer transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/tokens/transfer")]
+async fn transfer_token(req_data: ValidJson<AssetTransfer>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_transfer(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/tokens/batch-transfer",
+    tag = "tokens",
+    request_body = AssetBatchTransfer,
+    responses(
+        (status = 200, description = "Sent as one transaction", body = TxResponse),
+        (status = 202, description = "Too large for one transaction, sent as a chunked job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/tokens/batch-transfer")]
+async fn batch_transfer_tokens(req_data: ValidJson<AssetBatchTransfer>, config: Data<Config>, hub: Data<EventHub>, jobs: Data<BatchJobs>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_batch_transfer(req_data.into_inner(), &config, &hub, &jobs, &caller).await
 }
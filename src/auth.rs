@@ -0,0 +1,280 @@
+use crate::{error::ApiError, config::Config, jwt::JwtVerifier};
+use std::{fs, rc::Rc, str::FromStr, sync::Arc};
+use actix_web::{body::EitherBody, dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform}, http::{header::AUTHORIZATION, Method}, Error, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use sha2::{Digest, Sha256};
+use serde::{Serialize, Deserialize};
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Mint,
+    Transfer,
+    Wrap,
+    Admin
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Scope::Read => "read",
+            Scope::Mint => "mint",
+            Scope::Transfer => "transfer",
+            Scope::Wrap => "wrap",
+            Scope::Admin => "admin"
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        match scope.trim() {
+            "read" => Ok(Scope::Read),
+            "mint" => Ok(Scope::Mint),
+            "transfer" => Ok(Scope::Transfer),
+            "wrap" => Ok(Scope::Wrap),
+            "admin" => Ok(Scope::Admin),
+            scope => Err(format!("unknown scope \"{}\"", scope))
+        }
+    }
+}
+
+/// An API key as stored in configuration: only the SHA-256 hash of the key is kept.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    pub hash: String,
+    pub scopes: Vec<Scope>,
+    #[serde(default)]
+    pub signers: Vec<String>,
+}
+
+/// The authenticated client, available to handlers through the request extensions.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub name: String,
+    /// Identifies the credential itself (key hash, or JWT issuer and subject), unlike `name` which callers may share.
+    pub id: String,
+    pub scopes: Vec<Scope>,
+    pub signers: Vec<String>,
+}
+
+impl Caller {
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        match self.scopes.contains(&scope) {
+            true => Ok(()),
+            false => Err(ApiError::Forbidden(scope))
+        }
+    }
+}
+
+impl FromRequest for Caller {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Caller>().cloned().ok_or(ApiError::Unauthorized))
+    }
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Parses `name:sha256:scope,scope[:signer,signer];...` entries.
+pub fn parse_api_keys(keys: &str) -> Result<Vec<ApiKey>, String> {
+    keys.split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let parts: Vec<&str> = entry.trim().split(':').collect();
+            match parts.as_slice() {
+                [name, hash, scopes] | [name, hash, scopes, _] => Ok(ApiKey {
+                    name: name.to_string(),
+                    hash: hash.to_lowercase(),
+                    scopes: scopes.split(',').map(Scope::from_str).collect::<Result<Vec<Scope>, String>>()?,
+                    signers: match parts.get(3) {
+                        Some(signers) => signers.split(',').map(|signer| signer.trim().to_lowercase()).filter(|signer| !signer.is_empty()).collect(),
+                        None => [].to_vec()
+                    },
+                }),
+                _ => Err(format!("invalid API key entry \"{}\"", entry))
+            }
+        })
+        .collect()
+}
+
+pub fn load_api_keys(file: Option<String>, keys: Option<String>) -> Result<Vec<ApiKey>, String> {
+    let mut api_keys: Vec<ApiKey> = [].to_vec();
+
+    if let Some(file) = file {
+        let contents = fs::read_to_string(&file).map_err(|error| format!("{}: {}", file, error))?;
+        let file_keys: Vec<ApiKey> = serde_json::from_str(&contents).map_err(|error| format!("{}: {}", file, error))?;
+        api_keys.extend(file_keys);
+    }
+
+    if let Some(keys) = keys {
+        api_keys.extend(parse_api_keys(&keys)?);
+    }
+
+    Ok(api_keys)
+}
+
+/// Scope required by each route. Routes not listed need the strictest scope, `admin`, so a new route
+/// can't be reached by every key until it is added here.
+pub fn required_scope(method: &Method, path: &str) -> Scope {
+    let path = path.trim_start_matches('/');
+
+    if let Some(resource) = path.strip_prefix("v1/") {
+        return v1_scope(method, resource);
+    }
+
+    match path {
+        "mint_nft" | "create_mint_voucher" | "create_mint_airdrop" => Scope::Mint,
+        "transfer_nft" | "batch_transfer_nft" | "relay_meta_transfer" | "create_transfer_airdrop" => Scope::Transfer,
+        "wrap_1155" | "batch_wrap_1155" | "unwrap_1155" => Scope::Wrap,
+        "subscribe_webhook" | "unsubscribe_webhook" | "retry_webhook_dead_letters" => Scope::Admin,
+        "get_nfts" | "get_contract_nfts" | "get_nft_transfers" | "get_nft_transfers_by_block" |
+        "get_all_token_ids" | "get_contract_nft_transfers" | "get_nft_metadata" | "get_nft_owners" |
+        "get_token_id_metadata" | "get_token_id_owners" | "get_wrapped_1155" | "events" |
+        "get_webhooks" | "get_webhook_dead_letters" | "build_meta_transfer" | "verify_meta_transfer" |
+        "redeem_mint_voucher" | "get_batch_job" | "get_airdrop" | "get_airdrops" => Scope::Read,
+        _ => Scope::Admin
+    }
+}
+
+/// The API document and its UI are readable without credentials.
+pub fn is_public(path: &str) -> bool {
+    path == "/openapi.json" || path.starts_with("/swagger-ui")
+}
+
+/// `/v1` writes need the scope of their action and every `GET` is a read. Other requests, such as changes
+/// to webhooks, need `admin`.
+fn v1_scope(method: &Method, resource: &str) -> Scope {
+    match resource {
+        "tokens/mint" | "vouchers" | "airdrops/mint" => Scope::Mint,
+        "tokens/transfer" | "tokens/batch-transfer" | "meta-transfers/relay" | "airdrops/transfer" => Scope::Transfer,
+        "wraps" | "wraps/batch" | "unwraps" => Scope::Wrap,
+        "meta-transfers/build" | "meta-transfers/verify" | "vouchers/redeem" => Scope::Read,
+        _ if method == Method::GET => Scope::Read,
+        _ => Scope::Admin
+    }
+}
+
+enum Credentials {
+    ApiKey(String),
+    Bearer(String),
+}
+
+fn request_credentials(req: &ServiceRequest) -> Option<Credentials> {
+    if let Some(key) = req.headers().get(API_KEY_HEADER) {
+        return key.to_str().ok().map(|key| Credentials::ApiKey(key.to_string()));
+    }
+
+    let authorization = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+
+    if let Some(key) = authorization.strip_prefix("ApiKey ") {
+        return Some(Credentials::ApiKey(key.trim().to_string()));
+    }
+
+    authorization.strip_prefix("Bearer ").map(|token| Credentials::Bearer(token.trim().to_string()))
+}
+
+pub async fn authorize(keys: &[ApiKey], jwt: Option<&JwtVerifier>, req: &ServiceRequest) -> Result<Caller, ApiError> {
+    let caller = match (request_credentials(req), jwt) {
+        (Some(Credentials::ApiKey(key)), _) => {
+            let hash = hash_api_key(&key);
+            match keys.iter().find(|api_key| api_key.hash == hash) {
+                Some(api_key) => Caller { name: api_key.name.to_owned(), id: format!("key:{}", api_key.hash), scopes: api_key.scopes.to_vec(), signers: api_key.signers.to_vec() },
+                None => return Err(ApiError::Unauthorized),
+            }
+        },
+        (Some(Credentials::Bearer(token)), Some(jwt)) => jwt.verify(&token).await?,
+        _ => return Err(ApiError::Unauthorized),
+    };
+
+    caller.require(required_scope(req.method(), req.path()))?;
+
+    Ok(caller)
+}
+
+/// Authenticates every request with an API key or, when configured, a JWT bearer token.
+pub struct Authentication {
+    keys: Rc<Vec<ApiKey>>,
+    jwt: Option<Arc<JwtVerifier>>,
+    disabled: bool,
+}
+
+impl Authentication {
+    pub fn new(config: &Config, jwt: Option<Arc<JwtVerifier>>) -> Self {
+        Authentication {
+            keys: Rc::new(config.api_keys.to_vec()),
+            jwt,
+            disabled: config.auth_disabled,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AuthenticationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticationMiddleware {
+            service: Rc::new(service),
+            keys: self.keys.clone(),
+            jwt: self.jwt.clone(),
+            disabled: self.disabled,
+        }))
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: Rc<S>,
+    keys: Rc<Vec<ApiKey>>,
+    jwt: Option<Arc<JwtVerifier>>,
+    disabled: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let keys = self.keys.clone();
+        let jwt = self.jwt.clone();
+        let disabled = self.disabled;
+
+        Box::pin(async move {
+            if !disabled && !is_public(req.path()) {
+                match authorize(&keys, jwt.as_deref(), &req).await {
+                    Ok(caller) => {
+                        req.extensions_mut().insert(caller);
+                    },
+                    Err(error) => return Ok(req.error_response(error).map_into_right_body())
+                }
+            }
+
+            service.call(req).await.map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
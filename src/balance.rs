@@ -0,0 +1,149 @@
+use crate::{config::{Config, ConfigErrors, non_empty}, error::ApiError, asset::get_web3};
+use std::time::Duration;
+use ethcontract::prelude::*;
+use actix_web::rt::time::sleep;
+use prometheus::{GaugeVec, Opts, Registry};
+use serde::Serialize;
+use serde_json::json;
+use log::{error, warn, info};
+
+const WEI_PER_ETH: f64 = 1e18;
+
+#[derive(Clone, Debug)]
+pub struct BalanceConfig {
+    pub poll_interval: u64,
+    pub low_threshold: U256,
+    pub alert_url: Option<String>,
+    pub refuse_writes: bool,
+}
+
+fn eth_to_wei(eth: f64) -> U256 {
+    U256::from((eth * 1e9) as u128) * U256::exp10(9)
+}
+
+fn wei_to_eth(wei: U256) -> f64 {
+    wei.low_u128() as f64 / WEI_PER_ETH
+}
+
+/// Reads the `BALANCE_*` variables; the threshold is given in ETH.
+pub fn init_balance(errors: &mut ConfigErrors) -> BalanceConfig {
+    BalanceConfig {
+        poll_interval: errors.parse::<u64>("BALANCE_POLL_INTERVAL", 60),
+        low_threshold: eth_to_wei(errors.parse::<f64>("BALANCE_LOW_THRESHOLD", 0.05)),
+        alert_url: non_empty("BALANCE_ALERT_URL"),
+        refuse_writes: errors.parse::<bool>("BALANCE_REFUSE_WRITES", false),
+    }
+}
+
+/// Balance of one signing account on one chain, in ETH.
+#[derive(Serialize, Debug)]
+pub struct SignerBalance {
+    pub chain: String,
+    pub account: String,
+    pub address: String,
+    pub balance: f64,
+    pub low: bool,
+}
+
+/// Reads the balance of every signing account on every chain once, sorted by chain and account name.
+pub async fn signer_balances(config: &Config) -> Result<Vec<SignerBalance>, ApiError> {
+    let mut balances: Vec<SignerBalance> = Vec::new();
+
+    for chain in config.chains.all() {
+        let web3 = get_web3(chain)?;
+
+        for (name, signer) in config.accounts.on_chain(chain) {
+            let address = signer.address();
+
+            let balance = web3.eth().balance(address, None).await.map_err(|_| ApiError::TransportError)?;
+
+            balances.push(SignerBalance {
+                chain: chain.name.to_owned(),
+                account: name.to_owned(),
+                address: format!("0x{:x}", address),
+                balance: wei_to_eth(balance),
+                low: balance < config.balance.low_threshold,
+            });
+        }
+    }
+
+    balances.sort_by(|a, b| (&a.chain, &a.account).cmp(&(&b.chain, &b.account)));
+
+    Ok(balances)
+}
+
+pub fn balance_gauge(registry: &Registry) -> GaugeVec {
+    let gauge = GaugeVec::new(
+        Opts::new("signer_balance_eth", "Native balance of each signing account").namespace("api"),
+        &["chain", "account", "address"]
+    ).unwrap();
+
+    registry.register(Box::new(gauge.clone())).unwrap();
+
+    gauge
+}
+
+async fn send_alert(url: &str, chain: &str, account: &str, address: H160, balance: U256, threshold: U256, low: bool) {
+
+    let awc_client = awc::Client::new();
+
+    let alert = json!({
+        "alert": if low { "low_balance" } else { "balance_recovered" },
+        "chain": chain,
+        "account": account,
+        "address": format!("0x{:x}", address),
+        "balance": wei_to_eth(balance),
+        "threshold": wei_to_eth(threshold)
+    });
+
+    match awc_client.post(url).send_json(&alert).await {
+        Ok(response) if response.status().is_success() => (),
+        Ok(response) => error!("Balance alert responded with status {}", response.status()),
+        Err(error) => error!("Sending balance alert failed: {}", error),
+    }
+}
+
+/// Polls every signing account on every chain, exports its balance and alerts once when it crosses the threshold.
+pub async fn watch(config: Config, gauge: GaugeVec) {
+
+    loop {
+        for chain in config.chains.all() {
+            let web3 = match get_web3(chain) {
+                Ok(web3) => web3,
+                Err(error) => {
+                    error!("{}", error);
+                    continue;
+                }
+            };
+
+            for (name, signer) in config.accounts.on_chain(chain) {
+                let address = signer.address();
+
+                let balance = match web3.eth().balance(address, None).await {
+                    Ok(balance) => balance,
+                    Err(error) => {
+                        error!("Fetching balance of {} on {} failed: {}", name, chain.name, error);
+                        continue;
+                    }
+                };
+
+                gauge.with_label_values(&[chain.name.as_str(), name.as_str(), &format!("0x{:x}", address)]).set(wei_to_eth(balance));
+
+                let low = balance < config.balance.low_threshold;
+
+                if config.accounts.set_low_balance(chain.id, address, low) {
+                    match low {
+                        true => warn!("Signer {} (0x{:x}) balance {} ETH on {} is below the threshold", name, address, wei_to_eth(balance), chain.name),
+                        false => info!("Signer {} (0x{:x}) balance on {} recovered", name, address, chain.name),
+                    }
+
+                    if let Some(url) = &config.balance.alert_url {
+                        send_alert(url, &chain.name, name, address, balance, config.balance.low_threshold, low).await;
+                    }
+                }
+            }
+        }
+
+        sleep(Duration::from_secs(config.balance.poll_interval)).await;
+    }
+}
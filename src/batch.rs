@@ -0,0 +1,342 @@
+use crate::{error::ApiError, config::{Config, ConfigErrors}, chain::ChainConfig, extract::ValidJson, stream::EventHub, signer::Signer, asset::{AssetData, AssetBatchTransfer, asset_batch_transfer_nft, asset_batch_transfer_gas, tx_response, TxResponse}};
+use std::{collections::HashMap, ops::Range, sync::{Mutex, atomic::{AtomicU64, Ordering}}};
+use ethcontract::prelude::*;
+use actix_web::{get, post, rt::spawn, web::{Data, Json, Path}, HttpResponse, Responder};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Clone, Debug)]
+pub struct BatchConfig {
+    pub max_size: usize,
+    pub max_gas: u64,
+}
+
+pub fn init_batch(errors: &mut ConfigErrors) -> BatchConfig {
+    BatchConfig {
+        max_size: errors.at_least::<usize>("BATCH_MAX_SIZE", 100, 1),
+        max_gas: errors.at_least::<u64>("BATCH_MAX_GAS", 10_000_000, 1),
+    }
+}
+
+/// One rejected entry of a batch request, reported back to the caller by index.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct BatchIssue {
+    pub index: usize,
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl BatchIssue {
+    pub fn new(index: usize, field: &'static str, reason: String) -> Self {
+        BatchIssue { index, field, reason }
+    }
+}
+
+/// Rejects a batch before any gas is spent: every index needs an id, an amount and
+/// data, amounts must be non-zero and ids unique unless `allow_duplicate_ids` is set.
+/// An oversized batch is reported at its first entry past `BATCH_MAX_SIZE`.
+pub fn validate_batch(config: &BatchConfig, ids: &[u64], amounts: &[u64], data: &[AssetData], allow_duplicate_ids: bool) -> Result<(), ApiError> {
+    let mut issues: Vec<BatchIssue> = Vec::new();
+
+    let size = ids.len().max(amounts.len()).max(data.len());
+
+    if size == 0 {
+        return Err(ApiError::InvalidBatch([BatchIssue::new(0, "ids", "batch is empty".to_string())].to_vec()));
+    }
+    if size > config.max_size {
+        let field = match size {
+            size if size == ids.len() => "ids",
+            size if size == amounts.len() => "amounts",
+            _ => "data",
+        };
+        issues.push(BatchIssue::new(config.max_size, field, format!("batch is larger than the maximum of {} entries", config.max_size)));
+    }
+
+    let mut seen: HashMap<u64, usize> = HashMap::new();
+
+    for index in 0..size {
+        match ids.get(index) {
+            Some(id) => match seen.get(id) {
+                Some(first) if !allow_duplicate_ids => issues.push(BatchIssue::new(index, "ids", format!("id {} is already used at index {}", id, first))),
+                Some(_) => (),
+                None => { seen.insert(*id, index); }
+            },
+            None => issues.push(BatchIssue::new(index, "ids", "missing id".to_string())),
+        }
+
+        match amounts.get(index) {
+            Some(0) => issues.push(BatchIssue::new(index, "amounts", "amount must be greater than zero".to_string())),
+            Some(_) => (),
+            None => issues.push(BatchIssue::new(index, "amounts", "missing amount".to_string())),
+        }
+
+        if data.get(index).is_none() {
+            issues.push(BatchIssue::new(index, "data", "missing data".to_string()));
+        }
+    }
+
+    match issues.is_empty() {
+        true => Ok(()),
+        false => Err(ApiError::InvalidBatch(issues))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkState {
+    Queued,
+    Submitted,
+    Failed,
+    Skipped
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BatchChunk {
+    pub ids: Vec<u64>,
+    pub amounts: Vec<u64>,
+    pub gas: u64,
+    pub state: ChunkState,
+    #[schema(value_type = Option<String>)]
+    pub tx: Option<H256>,
+    pub error: Option<String>,
+}
+
+/// A batch that was too large for one transaction, sent as consecutive chunks.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct BatchJob {
+    pub id: u64,
+    pub action: String,
+    pub state: JobState,
+    pub chunks: Vec<BatchChunk>,
+}
+
+/// Answer of a batch route: the transaction when the batch fit in one, otherwise the chunked job.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum BatchResponse {
+    Sent(TxResponse),
+    Job(BatchJob),
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct BatchJobId {
+    pub id: u64,
+}
+
+#[derive(Default)]
+pub struct BatchJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, BatchJob>>,
+}
+
+impl BatchJobs {
+    fn create(&self, action: &str, chunks: Vec<BatchChunk>) -> BatchJob {
+        let job = BatchJob {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+            action: action.to_string(),
+            state: JobState::Running,
+            chunks,
+        };
+
+        self.jobs.lock().unwrap().insert(job.id, job.clone());
+
+        job
+    }
+
+    fn update<F: FnOnce(&mut BatchJob)>(&self, id: u64, update: F) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            update(job);
+        }
+    }
+}
+
+/// Node messages for an estimate that ran out of gas or hit the block gas limit, as opposed to a revert.
+const GAS_LIMIT_ERRORS: &[&str] = &["gas required exceeds", "exceeds block gas limit", "out of gas"];
+
+fn exceeds_gas_limit(error: &ApiError) -> bool {
+    let message = error.to_string().to_lowercase();
+
+    GAS_LIMIT_ERRORS.iter().any(|reason| message.contains(reason))
+}
+
+/// Splits `transfer` into ranges whose estimated gas stays under `BATCH_MAX_GAS`, halving any range that doesn't fit.
+/// An estimate that fails on the gas limit counts as not fitting, since nodes reject estimates above the block gas
+/// limit; any other failure, like a revert, is returned at once.
+pub async fn split_batch(config: &Config, signer: &Signer, transfer: &AssetBatchTransfer) -> Result<Vec<(Range<usize>, U256)>, ApiError> {
+    let mut pending: Vec<Range<usize>> = [0..transfer.ids.len()].to_vec();
+    let mut chunks: Vec<(Range<usize>, U256)> = Vec::new();
+
+    while let Some(range) = pending.pop() {
+        match asset_batch_transfer_gas(config, signer, &transfer.chunk(range.clone())).await {
+            Ok(gas) if gas <= U256::from(config.batch.max_gas) => chunks.push((range, gas)),
+            Ok(gas) if range.len() == 1 => {
+                return Err(ApiError::InvalidBatch([BatchIssue::new(range.start, "ids", format!("entry alone needs {} gas, above the limit of {}", gas, config.batch.max_gas))].to_vec()));
+            },
+            Err(error) if range.len() == 1 || !exceeds_gas_limit(&error) => return Err(error),
+            _ => {
+                let middle = range.start + range.len() / 2;
+                pending.push(middle..range.end);
+                pending.push(range.start..middle);
+            }
+        }
+    }
+
+    Ok(chunks)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_batch_job(config: Data<Config>, jobs: Data<BatchJobs>, hub: Data<EventHub>, chain: String, signer: Signer, id: u64, action: String, transfer: AssetBatchTransfer, ranges: Vec<Range<usize>>) {
+    for (index, range) in ranges.into_iter().enumerate() {
+        let chunk = transfer.chunk(range);
+
+        match asset_batch_transfer_nft(&config, &signer, &chunk).await {
+            Ok(tx) => {
+                hub.track(tx, &chain, &action, [chunk.from.to_owned(), chunk.to.to_owned()].to_vec(), chunk.ids.to_vec());
+                jobs.update(id, |job| {
+                    job.chunks[index].state = ChunkState::Submitted;
+                    job.chunks[index].tx = Some(tx);
+                });
+            },
+            Err(error) => {
+                jobs.update(id, |job| {
+                    job.chunks[index].state = ChunkState::Failed;
+                    job.chunks[index].error = Some(error.to_string());
+                    for chunk in job.chunks.iter_mut().skip(index + 1) {
+                        chunk.state = ChunkState::Skipped;
+                    }
+                    job.state = JobState::Failed;
+                });
+                return;
+            }
+        }
+    }
+
+    jobs.update(id, |job| job.state = JobState::Completed);
+}
+
+/// Sends `transfer` as a single transaction when it fits, otherwise starts a chunked job and answers `202` with it.
+/// `chain` is the chain `transfer` was resolved to by the handler.
+pub async fn send_batch(config: &Data<Config>, jobs: &Data<BatchJobs>, hub: &Data<EventHub>, chain: &ChainConfig, signer: &Signer, transfer: AssetBatchTransfer, action: &str) -> Result<HttpResponse, ApiError> {
+    validate_batch(&config.batch, &transfer.ids, &transfer.amounts, &transfer.data, transfer.allow_duplicate_ids)?;
+
+    let chunks = split_batch(config, signer, &transfer).await?;
+
+    if chunks.len() == 1 {
+        let tx = asset_batch_transfer_nft(config, signer, &transfer).await?;
+
+        hub.track(tx, &chain.name, action, [transfer.from.to_owned(), transfer.to.to_owned()].to_vec(), transfer.ids.to_vec());
+
+        return Ok(HttpResponse::Ok().json(tx_response(tx).into_inner()));
+    }
+
+    let job = jobs.create(action, chunks.iter().map(|(range, gas)| BatchChunk {
+        ids: transfer.ids[range.clone()].to_vec(),
+        amounts: transfer.amounts[range.clone()].to_vec(),
+        gas: gas.low_u64(),
+        state: ChunkState::Queued,
+        tx: None,
+        error: None,
+    }).collect());
+
+    let ranges = chunks.into_iter().map(|(range, _)| range).collect();
+
+    spawn(run_batch_job(config.clone(), jobs.clone(), hub.clone(), chain.name.to_owned(), signer.clone(), job.id, action.to_string(), transfer, ranges));
+
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+fn find_job(jobs: &BatchJobs, id: u64) -> Result<Json<BatchJob>, ApiError> {
+    match jobs.jobs.lock().unwrap().get(&id) {
+        Some(job) => Ok(Json(job.clone())),
+        None => Err(ApiError::JobNotFound(id)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_batch_job",
+    tag = "legacy",
+    request_body = BatchJobId,
+    responses(
+        (status = 200, description = "Chunked batch job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("get_batch_job")]
+async fn get_batch_job(req_data: ValidJson<BatchJobId>, jobs: Data<BatchJobs>) -> Result<impl Responder, ApiError> {
+    find_job(&jobs, req_data.id)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/jobs/{id}",
+    tag = "jobs",
+    params(
+        ("id" = u64, Path, description = "Job id")
+    ),
+    responses(
+        (status = 200, description = "Chunked batch job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/jobs/{id}")]
+async fn get_job(id: Path<u64>, jobs: Data<BatchJobs>) -> Result<impl Responder, ApiError> {
+    find_job(&jobs, id.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: BatchConfig = BatchConfig { max_size: 3, max_gas: 10_000_000 };
+
+    fn data(count: usize) -> Vec<AssetData> {
+        (0..count).map(|_| AssetData { name: "Sugar".to_string(), symbol: "SGR".to_string(), decimals: 0 }).collect()
+    }
+
+    fn issues(result: Result<(), ApiError>) -> Vec<(usize, &'static str)> {
+        match result {
+            Err(ApiError::InvalidBatch(issues)) => issues.into_iter().map(|issue| (issue.index, issue.field)).collect(),
+            other => panic!("expected InvalidBatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_a_batch_at_the_size_limit() {
+        assert!(validate_batch(&CONFIG, &[1, 2, 3], &[1, 1, 1], &data(3), false).is_ok());
+    }
+
+    #[test]
+    fn reports_an_oversize_batch_at_its_first_extra_entry() {
+        assert_eq!(issues(validate_batch(&CONFIG, &[1, 2, 3, 4], &[1, 1, 1, 1], &data(4), false)), [(3, "ids")]);
+        assert_eq!(issues(validate_batch(&CONFIG, &[1, 2, 3], &[1, 1, 1, 1], &data(3), false)), [(3, "amounts"), (3, "ids"), (3, "data")]);
+    }
+
+    #[test]
+    fn reports_missing_entries_when_lengths_differ() {
+        assert_eq!(issues(validate_batch(&CONFIG, &[1, 2], &[1], &data(2), false)), [(1, "amounts")]);
+        assert_eq!(issues(validate_batch(&CONFIG, &[1], &[1, 1], &data(1), false)), [(1, "ids"), (1, "data")]);
+    }
+
+    #[test]
+    fn rejects_zero_amounts_and_duplicate_ids() {
+        assert_eq!(issues(validate_batch(&CONFIG, &[1, 1], &[1, 0], &data(2), false)), [(1, "ids"), (1, "amounts")]);
+        assert!(validate_batch(&CONFIG, &[1, 1], &[1, 1], &data(2), true).is_ok());
+    }
+
+    #[test]
+    fn only_gas_limit_failures_split_a_batch() {
+        assert!(exceeds_gas_limit(&ApiError::SignerError("gas required exceeds allowance (30000000)".to_string())));
+        assert!(exceeds_gas_limit(&ApiError::SignerError("Transaction ran out of gas".to_string())));
+        assert!(!exceeds_gas_limit(&ApiError::SignerError("execution reverted: ERC1155: insufficient balance for transfer".to_string())));
+    }
+}
@@ -0,0 +1,351 @@
+use sugarfunge_integration::{config::{self, Config}, error::ApiError, signer::SignerRoute, balance::signer_balances};
+use sugarfunge_integration::asset::{AssetData, AssetMint, AssetTransfer, asset_mint_nft, asset_transfer_nft};
+#[cfg(feature = "wrapper")]
+use sugarfunge_integration::wrapper::{GetWrapped1155, Unwrap1155, wrapper_unwrap, wrapper_get_wrapped};
+#[cfg(feature = "moralis")]
+use sugarfunge_integration::moralis::{NftQuery, QueryParams, moralis_query};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+use dotenv::dotenv;
+
+/// Operator commands that call the contracts and Moralis directly with the server's configuration.
+#[derive(Parser)]
+#[command(name = "sugarfunge-cli", version)]
+struct Cli {
+    /// How results are printed
+    #[arg(long, value_enum, default_value_t = Output::Table, global = true)]
+    output: Output,
+    /// TOML or YAML configuration file (Default: CONFIG_FILE)
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// Chain name, chain id or Moralis slug to run against (Default: the default chain)
+    #[arg(long, global = true)]
+    chain: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Output {
+    Table,
+    Json
+}
+
+#[derive(Args)]
+struct Data {
+    /// Asset name
+    #[arg(long)]
+    name: String,
+    /// Asset symbol
+    #[arg(long)]
+    symbol: String,
+    /// Asset decimals
+    #[arg(long, default_value_t = 0)]
+    decimals: u64,
+}
+
+impl From<Data> for AssetData {
+    fn from(data: Data) -> Self {
+        AssetData {
+            name: data.name,
+            symbol: data.symbol,
+            decimals: data.decimals,
+        }
+    }
+}
+
+#[cfg(feature = "moralis")]
+#[derive(Args)]
+struct Options {
+    /// Moralis number format (Default: decimal)
+    #[arg(long)]
+    format: Option<String>,
+    #[arg(long)]
+    offset: Option<u64>,
+    #[arg(long)]
+    limit: Option<u64>,
+}
+
+#[cfg(feature = "moralis")]
+impl Options {
+    fn params(self, chain: &Option<String>) -> QueryParams {
+        QueryParams {
+            chain: chain.to_owned(),
+            format: self.format,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Mint tokens to an account
+    Mint {
+        #[arg(long)]
+        account: String,
+        #[arg(long)]
+        id: u64,
+        #[arg(long)]
+        amount: u64,
+        #[command(flatten)]
+        data: Data,
+        /// Signing account (Default: MINT_SIGNER)
+        #[arg(long)]
+        signer: Option<String>,
+    },
+    /// Transfer tokens between accounts
+    Transfer {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        id: u64,
+        #[arg(long)]
+        amount: u64,
+        #[command(flatten)]
+        data: Data,
+        /// Signing account (Default: TRANSFER_SIGNER)
+        #[arg(long)]
+        signer: Option<String>,
+    },
+    /// Unwrap ERC20 wrapped tokens back to the recipient
+    #[cfg(feature = "wrapper")]
+    Unwrap {
+        #[arg(long)]
+        id: u64,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        recipient: String,
+        #[command(flatten)]
+        data: Data,
+        /// Signing account (Default: WRAP_SIGNER)
+        #[arg(long)]
+        signer: Option<String>,
+    },
+    /// Print the address of the ERC20 wrapping a token
+    #[cfg(feature = "wrapper")]
+    Wrapped {
+        #[arg(long)]
+        id: u64,
+        #[command(flatten)]
+        data: Data,
+    },
+    /// Print the balance of every signing account on every chain
+    Balances,
+    /// NFTs owned by an account
+    #[cfg(feature = "moralis")]
+    Nfts {
+        address: String,
+        /// Only NFTs of this contract
+        #[arg(long)]
+        contract: Option<String>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// NFT transfers to or from an account
+    #[cfg(feature = "moralis")]
+    Transfers {
+        address: String,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// NFT transfers in a block
+    #[cfg(feature = "moralis")]
+    BlockTransfers {
+        block: u64,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Token ids of a contract
+    #[cfg(feature = "moralis")]
+    Tokens {
+        token_address: String,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Transfers of a contract
+    #[cfg(feature = "moralis")]
+    ContractTransfers {
+        token_address: String,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Metadata of a contract, or of one token with --id
+    #[cfg(feature = "moralis")]
+    Metadata {
+        token_address: String,
+        #[arg(long)]
+        id: Option<u64>,
+        #[command(flatten)]
+        options: Options,
+    },
+    /// Owners of a contract's tokens, or of one token with --id
+    #[cfg(feature = "moralis")]
+    Owners {
+        token_address: String,
+        #[arg(long)]
+        id: Option<u64>,
+        #[command(flatten)]
+        options: Options,
+    },
+}
+
+async fn run(config: &Config, chain: Option<String>, command: Command) -> Result<Value, ApiError> {
+    let selected = config.chains.select(&chain)?;
+
+    match command {
+        Command::Mint { account, id, amount, data, signer } => {
+            let signer = config.accounts.select(SignerRoute::Mint, &signer, &None, selected)?;
+            let mint = AssetMint { account, amount, id, data: data.into(), chain, signer: None };
+
+            let tx = asset_mint_nft(config, signer, &mint).await?;
+
+            Ok(json!({ "tx": format!("0x{:x}", tx) }))
+        },
+        Command::Transfer { from, to, id, amount, data, signer } => {
+            let signer = config.accounts.select(SignerRoute::Transfer, &signer, &None, selected)?;
+            let transfer = AssetTransfer { from, to, amount, id, data: data.into(), chain, signer: None };
+
+            let tx = asset_transfer_nft(config, signer, &transfer).await?;
+
+            Ok(json!({ "tx": format!("0x{:x}", tx) }))
+        },
+        #[cfg(feature = "wrapper")]
+        Command::Unwrap { id, amount, recipient, data, signer } => {
+            let signer = config.accounts.select(SignerRoute::Wrap, &signer, &None, selected)?;
+            let unwrap = Unwrap1155 { id, amount, recipient_address: recipient, data: data.into(), chain, signer: None };
+
+            let tx = wrapper_unwrap(config, signer, &unwrap).await?;
+
+            Ok(json!({ "tx": format!("0x{:x}", tx) }))
+        },
+        #[cfg(feature = "wrapper")]
+        Command::Wrapped { id, data } => {
+            let address = wrapper_get_wrapped(config, &GetWrapped1155 { id, data: data.into(), chain }).await?;
+
+            Ok(json!({ "address": format!("0x{:x}", address) }))
+        },
+        Command::Balances => Ok(serde_json::to_value(signer_balances(config).await?)?),
+        #[cfg(feature = "moralis")]
+        Command::Nfts { address, contract, options } => {
+            let query = match contract {
+                Some(token_address) => NftQuery::AccountContractNfts(address, token_address),
+                None => NftQuery::AccountNfts(address),
+            };
+
+            moralis_query(config, &query, &options.params(&chain)).await
+        },
+        #[cfg(feature = "moralis")]
+        Command::Transfers { address, options } => moralis_query(config, &NftQuery::AccountTransfers(address), &options.params(&chain)).await,
+        #[cfg(feature = "moralis")]
+        Command::BlockTransfers { block, options } => moralis_query(config, &NftQuery::BlockTransfers(block), &options.params(&chain)).await,
+        #[cfg(feature = "moralis")]
+        Command::Tokens { token_address, options } => moralis_query(config, &NftQuery::ContractTokens(token_address), &options.params(&chain)).await,
+        #[cfg(feature = "moralis")]
+        Command::ContractTransfers { token_address, options } => moralis_query(config, &NftQuery::ContractTransfers(token_address), &options.params(&chain)).await,
+        #[cfg(feature = "moralis")]
+        Command::Metadata { token_address, id, options } => {
+            let query = match id {
+                Some(id) => NftQuery::TokenMetadata(token_address, id),
+                None => NftQuery::ContractMetadata(token_address),
+            };
+
+            moralis_query(config, &query, &options.params(&chain)).await
+        },
+        #[cfg(feature = "moralis")]
+        Command::Owners { token_address, id, options } => {
+            let query = match id {
+                Some(id) => NftQuery::TokenOwners(token_address, id),
+                None => NftQuery::ContractOwners(token_address),
+            };
+
+            moralis_query(config, &query, &options.params(&chain)).await
+        },
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.to_owned(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn print_rows(headers: &[String], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = headers.iter().enumerate()
+        .map(|(index, header)| rows.iter().map(|row| row[index].chars().count()).max().unwrap_or(0).max(header.chars().count()))
+        .collect();
+
+    let line = |cells: &[String]| cells.iter().zip(&widths).map(|(cell, width)| format!("{:width$}", cell, width = width)).collect::<Vec<String>>().join("  ");
+
+    println!("{}", line(headers).trim_end());
+    println!("{}", widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<String>>().join("  "));
+    for row in rows {
+        println!("{}", line(row).trim_end());
+    }
+}
+
+/// Lists become one row per item with a column per field; Moralis pages print their `result` list.
+fn print_table(value: &Value) {
+    let items = match value {
+        Value::Array(items) => Some(items),
+        Value::Object(object) => match object.get("result") {
+            Some(Value::Array(items)) => Some(items),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match (items, value) {
+        (Some(items), _) => {
+            let mut headers: Vec<String> = Vec::new();
+            for item in items {
+                if let Value::Object(object) = item {
+                    for key in object.keys() {
+                        if !headers.contains(key) {
+                            headers.push(key.to_owned());
+                        }
+                    }
+                }
+            }
+
+            let rows: Vec<Vec<String>> = items.iter()
+                .map(|item| headers.iter().map(|header| item.get(header).map(cell).unwrap_or_default()).collect())
+                .collect();
+
+            print_rows(&headers, &rows);
+        },
+        (None, Value::Object(object)) => {
+            let rows: Vec<Vec<String>> = object.iter().map(|(key, value)| [key.to_owned(), cell(value)].to_vec()).collect();
+
+            print_rows(&["field".to_string(), "value".to_string()], &rows);
+        },
+        (None, value) => println!("{}", cell(value)),
+    }
+}
+
+#[actix_web::main]
+async fn main() {
+
+    dotenv().ok();
+    env_logger::init();
+
+    let cli = Cli::parse();
+
+    let config = config::init(cli.config);
+
+    match run(&config, cli.chain, cli.command).await {
+        Ok(value) => match cli.output {
+            Output::Table => print_table(&value),
+            Output::Json => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+        },
+        Err(error) => {
+            eprintln!("{}: {}", error.name(), error);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{error::ApiError, config::{ConfigErrors, prefixed, non_empty}, contracts::{ContractsConfig, init_contracts}};
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_CHAIN: &str = "default";
+
+/// Networks with a known Moralis slug and Infura host, used for the defaults of a chain with that id.
+pub const KNOWN_CHAINS: &[KnownChain] = &[
+    KnownChain { id: 1, name: "mainnet", moralis_chain: "eth", infura_host: "mainnet" },
+    KnownChain { id: 11155111, name: "sepolia", moralis_chain: "sepolia", infura_host: "sepolia" },
+    KnownChain { id: 137, name: "polygon", moralis_chain: "polygon", infura_host: "polygon-mainnet" },
+    KnownChain { id: 80002, name: "amoy", moralis_chain: "amoy", infura_host: "polygon-amoy" },
+    KnownChain { id: 56, name: "bsc", moralis_chain: "bsc", infura_host: "bsc-mainnet" },
+    KnownChain { id: 42161, name: "arbitrum", moralis_chain: "arbitrum", infura_host: "arbitrum-mainnet" },
+    KnownChain { id: 10, name: "optimism", moralis_chain: "optimism", infura_host: "optimism-mainnet" },
+    KnownChain { id: 8453, name: "base", moralis_chain: "base", infura_host: "base-mainnet" },
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct KnownChain {
+    pub id: u64,
+    pub name: &'static str,
+    pub moralis_chain: &'static str,
+    pub infura_host: &'static str,
+}
+
+pub fn known_chain(id: u64) -> Option<&'static KnownChain> {
+    KNOWN_CHAINS.iter().find(|chain| chain.id == id)
+}
+
+/// A network the service reads from and writes to, with its own RPC endpoint and contract deployment.
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    pub id: u64,
+    pub name: String,
+    pub rpc_url: String,
+    pub moralis_chain: String,
+    pub contracts: ContractsConfig,
+}
+
+impl ChainConfig {
+    /// Whether `chain` names this network, by its name, its chain id, its Moralis slug or its `KNOWN_CHAINS` name.
+    fn matches(&self, chain: &str) -> bool {
+        self.name == chain
+            || self.id.to_string() == chain
+            || self.moralis_chain.eq_ignore_ascii_case(chain)
+            || known_chain(self.id).map_or(false, |known| known.name == chain)
+    }
+}
+
+/// The chains served by this instance. Requests without a `chain` go to the default one, read from `CHAIN_*`.
+#[derive(Clone, Debug)]
+pub struct Chains(Vec<ChainConfig>);
+
+impl Chains {
+    pub fn default_chain(&self) -> &ChainConfig {
+        &self.0[0]
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &ChainConfig> {
+        self.0.iter()
+    }
+
+    /// The chain a request asked for, by name, chain id or Moralis slug, or the default chain.
+    pub fn select(&self, requested: &Option<String>) -> Result<&ChainConfig, ApiError> {
+        match requested {
+            Some(chain) => {
+                let chain = chain.trim().to_lowercase();
+                self.0.iter().find(|config| config.matches(&chain)).ok_or(ApiError::UnknownChain(chain))
+            },
+            None => Ok(self.default_chain()),
+        }
+    }
+}
+
+/// The `chain` query parameter of read routes that have no request body.
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChainQuery {
+    /// Chain name, chain id or Moralis slug (Default: the default chain)
+    pub chain: Option<String>,
+}
+
+/// Reads one chain. The default chain uses `CHAIN_ID`, `CHAIN_RPC_URL`, ...; named chains read the same
+/// variables with their upper-cased name as prefix, e.g. `POLYGON_CHAIN_ID`. The RPC URL and Moralis slug
+/// default to the `KNOWN_CHAINS` entry of the chain id.
+fn init_chain(prefix: &str, name: String, errors: &mut ConfigErrors) -> ChainConfig {
+    let id_var = prefixed(prefix, "CHAIN_ID");
+    let id = match non_empty(&id_var) {
+        Some(_) => errors.parse(&id_var, 0),
+        None => {
+            errors.push(format!("{} is not set", id_var));
+            0
+        }
+    };
+
+    let known = known_chain(id);
+    let rpc_url_var = prefixed(prefix, "CHAIN_RPC_URL");
+
+    let rpc_url = match (non_empty(&rpc_url_var), known) {
+        (Some(url), _) => url,
+        (None, Some(known)) => match non_empty("INFURA_PROJECT_ID") {
+            Some(project_id) => format!("https://{}.infura.io/v3/{}", known.infura_host, project_id),
+            None => {
+                errors.push(format!("{} or INFURA_PROJECT_ID is not set for {} ({})", rpc_url_var, known.name, id));
+                String::new()
+            }
+        },
+        (None, None) => errors.required(&rpc_url_var),
+    };
+
+    // Moralis takes the hex chain id for networks it has no slug for.
+    let moralis_chain = non_empty(&prefixed(prefix, "CHAIN_MORALIS_CHAIN")).unwrap_or_else(|| match known {
+        Some(known) => known.moralis_chain.to_string(),
+        None => format!("0x{:x}", id),
+    });
+
+    ChainConfig {
+        contracts: init_contracts(prefix, id, errors),
+        id,
+        name,
+        rpc_url,
+        moralis_chain,
+    }
+}
+
+/// Loads the default chain and the named chains listed in `CHAINS`, e.g. `CHAINS=polygon,mainnet`.
+pub fn init_chains(errors: &mut ConfigErrors) -> Chains {
+    let names: Vec<String> = non_empty("CHAINS").unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let default_name = non_empty("CHAIN_NAME").map(|name| name.trim().to_lowercase()).unwrap_or_else(|| DEFAULT_CHAIN.to_string());
+
+    let mut chains: Vec<ChainConfig> = [init_chain("", default_name, errors)].to_vec();
+
+    for name in names {
+        let chain = init_chain(&name.to_uppercase(), name, errors);
+
+        if let Some(other) = chains.iter().find(|other| other.name == chain.name || other.id == chain.id) {
+            errors.push(format!("chain {} ({}) is configured twice, also as {} ({})", chain.name, chain.id, other.name, other.id));
+            continue;
+        }
+
+        chains.push(chain);
+    }
+
+    Chains(chains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// The errors `init_chain` recorded about the RPC URL, leaving out contract settings.
+    fn rpc_errors(errors: ConfigErrors) -> Vec<String> {
+        errors.into_result(|| ()).err().unwrap_or_default().into_iter().filter(|error| error.contains("RPC_URL")).collect()
+    }
+
+    // One test, as every case reads the process-wide INFURA_PROJECT_ID.
+    #[test]
+    fn init_chain_defaults_from_the_chain_id() {
+        env::set_var("CHAINTEST_KNOWN_CHAIN_ID", "137");
+        env::set_var("CHAINTEST_UNKNOWN_CHAIN_ID", "31337");
+        env::set_var("CHAINTEST_UNKNOWN_CHAIN_RPC_URL", "http://localhost:8545");
+
+        env::set_var("INFURA_PROJECT_ID", "project");
+        let mut errors = ConfigErrors::default();
+        let known = init_chain("CHAINTEST_KNOWN", "known".to_string(), &mut errors);
+
+        assert_eq!(known.rpc_url, "https://polygon-mainnet.infura.io/v3/project");
+        assert_eq!(known.moralis_chain, "polygon");
+        assert!(rpc_errors(errors).is_empty());
+
+        let mut errors = ConfigErrors::default();
+        let unknown = init_chain("CHAINTEST_UNKNOWN", "unknown".to_string(), &mut errors);
+
+        assert_eq!(unknown.rpc_url, "http://localhost:8545");
+        assert_eq!(unknown.moralis_chain, "0x7a69");
+        assert!(rpc_errors(errors).is_empty());
+
+        // `.env.example` leaves INFURA_PROJECT_ID empty, which must read as missing too.
+        env::set_var("INFURA_PROJECT_ID", "");
+        let mut errors = ConfigErrors::default();
+        init_chain("CHAINTEST_KNOWN", "known".to_string(), &mut errors);
+
+        assert_eq!(rpc_errors(errors), ["CHAINTEST_KNOWN_CHAIN_RPC_URL or INFURA_PROJECT_ID is not set for polygon (137)"]);
+
+        env::remove_var("INFURA_PROJECT_ID");
+        let mut errors = ConfigErrors::default();
+        init_chain("CHAINTEST_KNOWN", "known".to_string(), &mut errors);
+
+        assert_eq!(rpc_errors(errors), ["CHAINTEST_KNOWN_CHAIN_RPC_URL or INFURA_PROJECT_ID is not set for polygon (137)"]);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    error::ErrorResponse,
+    auth::API_KEY_HEADER,
+    asset::{AssetMint, AssetTransfer, AssetBatchTransfer, TxResponse},
+    webhook::{Webhook, SubscribeWebhook, DeadLetter},
+    voucher::{CreateMintVoucher, RedeemMintVoucher},
+    batch::{BatchJob, BatchResponse},
+    airdrop::{AirdropEntry, AirdropOptions, AirdropJob, AirdropSummary},
+};
+use awc::{http::header::AUTHORIZATION, ClientRequest};
+use derive_more::Error;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+#[cfg(feature = "moralis")]
+use crate::moralis::QueryParams;
+#[cfg(feature = "relayer")]
+use crate::relayer::{MetaTransfer, SignedForwardRequest};
+#[cfg(feature = "wrapper")]
+use crate::{asset::AssetData, wrapper::{Wrap1155, BatchWrap1155, Unwrap1155}};
+
+/// Largest response body the client reads; full airdrop jobs and Moralis pages can be large.
+const BODY_LIMIT: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    Request(#[error(not(source))] String),
+    Decode(serde_json::Error),
+    Api(#[error(not(source))] ErrorResponse),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(reason) => write!(f, "Request failed: {}", reason),
+            Self::Decode(error) => write!(f, "Error when parsing JSON: {}", error.to_string()),
+            Self::Api(error) => write!(f, "{} ({}): {}", error.error, error.code, error.message),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Credentials {
+    ApiKey(String),
+    Bearer(String),
+}
+
+/// Typed client for the `/v1` routes of a running server. Like `awc`, it runs on the actix runtime.
+#[derive(Clone)]
+pub struct Client {
+    http: awc::Client,
+    base_url: String,
+    credentials: Option<Credentials>,
+}
+
+impl Client {
+    pub fn new(base_url: &str) -> Self {
+        Client {
+            http: awc::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials: None,
+        }
+    }
+
+    /// Authenticates every request with the `X-API-Key` header.
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.credentials = Some(Credentials::ApiKey(key.to_string()));
+        self
+    }
+
+    /// Authenticates every request with a JWT bearer token.
+    pub fn with_bearer_token(mut self, token: &str) -> Self {
+        self.credentials = Some(Credentials::Bearer(token.to_string()));
+        self
+    }
+
+    fn request(&self, request: ClientRequest) -> ClientRequest {
+        match &self.credentials {
+            Some(Credentials::ApiKey(key)) => request.insert_header((API_KEY_HEADER, key.to_owned())),
+            Some(Credentials::Bearer(token)) => request.insert_header((AUTHORIZATION, format!("Bearer {}", token))),
+            None => request,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/v1{}", self.base_url, path)
+    }
+
+    async fn send<R: DeserializeOwned>(&self, request: ClientRequest, body: Option<&[u8]>) -> Result<R, ClientError> {
+        let sent = match body {
+            Some(body) => self.request(request).send_body(body.to_vec()).await,
+            None => self.request(request).send().await,
+        };
+
+        let mut response = sent.map_err(|error| ClientError::Request(error.to_string()))?;
+
+        let bytes = response.body().limit(BODY_LIMIT).await.map_err(|error| ClientError::Request(error.to_string()))?;
+
+        if response.status().is_success() {
+            return serde_json::from_slice(&bytes).map_err(ClientError::Decode);
+        }
+
+        match serde_json::from_slice::<ErrorResponse>(&bytes) {
+            Ok(error) => Err(ClientError::Api(error)),
+            Err(_) => Err(ClientError::Request(format!("server answered {}", response.status()))),
+        }
+    }
+
+    async fn get<R: DeserializeOwned>(&self, path: &str) -> Result<R, ClientError> {
+        self.send(self.http.get(self.url(path)), None).await
+    }
+
+    async fn get_query<Q: Serialize, R: DeserializeOwned>(&self, path: &str, query: &Q) -> Result<R, ClientError> {
+        let request = self.http.get(self.url(path)).query(query).map_err(|error| ClientError::Request(error.to_string()))?;
+
+        self.send(request, None).await
+    }
+
+    async fn post<B: Serialize, R: DeserializeOwned>(&self, path: &str, body: &B) -> Result<R, ClientError> {
+        let body = serde_json::to_vec(body).map_err(ClientError::Decode)?;
+        let request = self.http.post(self.url(path)).content_type("application/json");
+
+        self.send(request, Some(&body)).await
+    }
+
+    async fn delete<R: DeserializeOwned>(&self, path: &str) -> Result<R, ClientError> {
+        self.send(self.http.delete(self.url(path)), None).await
+    }
+
+    pub async fn mint(&self, mint: &AssetMint) -> Result<TxResponse, ClientError> {
+        self.post("/tokens/mint", mint).await
+    }
+
+    pub async fn transfer(&self, transfer: &AssetTransfer) -> Result<TxResponse, ClientError> {
+        self.post("/tokens/transfer", transfer).await
+    }
+
+    pub async fn batch_transfer(&self, transfer: &AssetBatchTransfer) -> Result<BatchResponse, ClientError> {
+        self.post("/tokens/batch-transfer", transfer).await
+    }
+
+    pub async fn webhooks(&self) -> Result<Vec<Webhook>, ClientError> {
+        self.get("/webhooks").await
+    }
+
+    pub async fn subscribe_webhook(&self, subscription: &SubscribeWebhook) -> Result<Webhook, ClientError> {
+        self.post("/webhooks", subscription).await
+    }
+
+    pub async fn unsubscribe_webhook(&self, id: u64) -> Result<Webhook, ClientError> {
+        self.delete(&format!("/webhooks/{}", id)).await
+    }
+
+    pub async fn dead_letters(&self) -> Result<Vec<DeadLetter>, ClientError> {
+        self.get("/webhooks/dead-letters").await
+    }
+
+    pub async fn retry_dead_letters(&self) -> Result<Value, ClientError> {
+        self.post("/webhooks/dead-letters/retry", &()).await
+    }
+
+    /// The voucher, its `signature` and the EIP-712 `typed_data` it was signed as.
+    pub async fn create_voucher(&self, voucher: &CreateMintVoucher) -> Result<Value, ClientError> {
+        self.post("/vouchers", voucher).await
+    }
+
+    pub async fn redeem_voucher(&self, redeem: &RedeemMintVoucher) -> Result<TxResponse, ClientError> {
+        self.post("/vouchers/redeem", redeem).await
+    }
+
+    pub async fn job(&self, id: u64) -> Result<BatchJob, ClientError> {
+        self.get(&format!("/jobs/{}", id)).await
+    }
+
+    pub async fn mint_airdrop(&self, entries: &[AirdropEntry], options: &AirdropOptions) -> Result<AirdropSummary, ClientError> {
+        self.airdrop_request("/airdrops/mint", entries, options).await
+    }
+
+    pub async fn transfer_airdrop(&self, entries: &[AirdropEntry], options: &AirdropOptions) -> Result<AirdropSummary, ClientError> {
+        self.airdrop_request("/airdrops/transfer", entries, options).await
+    }
+
+    async fn airdrop_request(&self, path: &str, entries: &[AirdropEntry], options: &AirdropOptions) -> Result<AirdropSummary, ClientError> {
+        let body = serde_json::to_vec(entries).map_err(ClientError::Decode)?;
+        let request = self.http.post(self.url(path))
+            .content_type("application/json")
+            .query(options)
+            .map_err(|error| ClientError::Request(error.to_string()))?;
+
+        self.send(request, Some(&body)).await
+    }
+
+    pub async fn airdrops(&self) -> Result<Vec<AirdropSummary>, ClientError> {
+        self.get("/airdrops").await
+    }
+
+    pub async fn airdrop(&self, id: u64) -> Result<AirdropJob, ClientError> {
+        self.get(&format!("/airdrops/{}", id)).await
+    }
+}
+
+/// The Moralis query routes, served when the server is built with the `moralis` feature.
+#[cfg(feature = "moralis")]
+impl Client {
+    pub async fn account_nfts(&self, address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/accounts/{}/nfts", address), options).await
+    }
+
+    pub async fn account_contract_nfts(&self, address: &str, token_address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/accounts/{}/contracts/{}/nfts", address, token_address), options).await
+    }
+
+    pub async fn account_transfers(&self, address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/accounts/{}/transfers", address), options).await
+    }
+
+    pub async fn block_transfers(&self, block: u64, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/blocks/{}/transfers", block), options).await
+    }
+
+    pub async fn contract_tokens(&self, token_address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/tokens", token_address), options).await
+    }
+
+    pub async fn contract_transfers(&self, token_address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/transfers", token_address), options).await
+    }
+
+    pub async fn contract_metadata(&self, token_address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/metadata", token_address), options).await
+    }
+
+    pub async fn contract_owners(&self, token_address: &str, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/owners", token_address), options).await
+    }
+
+    pub async fn token_metadata(&self, token_address: &str, id: u64, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/tokens/{}", token_address, id), options).await
+    }
+
+    pub async fn token_owners(&self, token_address: &str, id: u64, options: &QueryParams) -> Result<Value, ClientError> {
+        self.get_query(&format!("/contracts/{}/tokens/{}/owners", token_address, id), options).await
+    }
+}
+
+/// The meta-transaction routes, served when the server is built with the `relayer` feature.
+#[cfg(feature = "relayer")]
+impl Client {
+    /// EIP-712 typed data of the forward request, for `from` to sign.
+    pub async fn build_meta_transfer(&self, transfer: &MetaTransfer) -> Result<Value, ClientError> {
+        self.post("/meta-transfers/build", transfer).await
+    }
+
+    pub async fn verify_meta_transfer(&self, signed: &SignedForwardRequest) -> Result<Value, ClientError> {
+        self.post("/meta-transfers/verify", signed).await
+    }
+
+    pub async fn relay_meta_transfer(&self, signed: &SignedForwardRequest) -> Result<TxResponse, ClientError> {
+        self.post("/meta-transfers/relay", signed).await
+    }
+}
+
+#[cfg(feature = "wrapper")]
+#[derive(Serialize)]
+struct WrappedQuery<'a> {
+    #[serde(flatten)]
+    data: &'a AssetData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chain: Option<&'a str>,
+}
+
+/// The ERC1155 wrapper routes, served when the server is built with the `wrapper` feature.
+#[cfg(feature = "wrapper")]
+impl Client {
+    pub async fn wrap_token(&self, token: &Wrap1155) -> Result<TxResponse, ClientError> {
+        self.post("/wraps", token).await
+    }
+
+    pub async fn batch_wrap_tokens(&self, token: &BatchWrap1155) -> Result<BatchResponse, ClientError> {
+        self.post("/wraps/batch", token).await
+    }
+
+    pub async fn unwrap_token(&self, unwrap: &Unwrap1155) -> Result<TxResponse, ClientError> {
+        self.post("/unwraps", unwrap).await
+    }
+
+    /// Address of the ERC20 wrapping token `id` on `chain` (Default: the server's default chain), returned in the `tx` field.
+    pub async fn wrapped(&self, id: u64, data: &AssetData, chain: Option<&str>) -> Result<TxResponse, ClientError> {
+        self.get_query(&format!("/wrapped/{}", id), &WrappedQuery { data, chain }).await
+    }
+}
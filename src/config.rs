@@ -1,43 +1,317 @@
-use std::env;
-use ethcontract::PrivateKey;
+use std::{cmp::Ordering, env, fmt::Display, fs, path::Path, str::FromStr};
+use serde_json::Value;
+use crate::{auth::{ApiKey, load_api_keys}, jwt::{JwtConfig, init_jwt}, ratelimit::{RateLimitConfig, init_rate_limit}, signer::{Accounts, init_accounts}, voucher::{VoucherConfig, init_voucher}, balance::{BalanceConfig, init_balance}, batch::{BatchConfig, init_batch}, airdrop::{AirdropConfig, init_airdrop}, chain::{Chains, init_chains}};
+#[cfg(feature = "moralis")]
+use crate::moralis::{MoralisConfig, init_moralis};
+#[cfg(feature = "relayer")]
+use crate::relayer::{RelayerConfig, init_relayer};
+
+/// File keys whose section doesn't match the prefix of their variable, e.g. `[auth] api_keys` is `API_KEYS`.
+const ALIASES: &[(&str, &str)] = &[
+    ("CHAIN_INFURA_", "INFURA_"),
+    ("AUTH_API_KEYS", "API_KEYS"),
+    ("AUTH_JWT_", "JWT_"),
+    ("AUTH_RATE_LIMIT_", "RATE_LIMIT_"),
+];
 
 #[derive(Clone, Debug)]
 pub struct Config {
-    pub listen_url: String,  
-    pub project_id: String,
-    pub private_key: PrivateKey,
-    pub chain_id: u64,
-    pub moralis_base_url: String,
-    pub moralis_api_key: String,
-}
-
-pub fn init() -> Config { 
-    let panic_message: String = "enviroment variable is not set".to_string();
-
-    Config {
-        listen_url: match env::var("LISTEN_URL") {
-            Ok(var) => var,
-            Err(_) => panic!("LISTEN_URL {}", panic_message)
-        },
-        project_id: match env::var("INFURA_PROJECT_ID") {
-            Ok(var) => var,
-            Err(_) => panic!("INFURA_PROJECT_ID {}", panic_message)
-        },
-        private_key: match env::var("PRIVATE_KEY") {
-            Ok(var) => var.parse().expect("invalid PK"),
-            Err(_) => panic!("PRIVATE_KEY {}", panic_message)
-        },
-        chain_id: match env::var("CHAIN_ID") {
-            Ok(var) => var.parse::<u64>().unwrap(),
-            Err(_) => panic!("CHAIN_ID {}", panic_message)
-        },
-        moralis_base_url: match env::var("MORALIS_BASE_URL") {
-            Ok(var) => var,
-            Err(_) => panic!("MORALIS_BASE_URL {}", panic_message)
-        },
-        moralis_api_key: match env::var("MORALIS_API_KEY") {
-            Ok(var) => var,
-            Err(_) => panic!("MORALIS_API_KEY {}", panic_message)
-        },
+    pub listen_url: String,
+    pub accounts: Accounts,
+    pub chains: Chains,
+    #[cfg(feature = "moralis")]
+    pub moralis: Option<MoralisConfig>,
+    pub webhook_secret: Option<String>,
+    pub webhook_max_retries: u32,
+    pub event_poll_interval: u64,
+    pub event_confirmations: u64,
+    pub json_limit: usize,
+    pub batch: BatchConfig,
+    pub airdrop: AirdropConfig,
+    pub api_keys: Vec<ApiKey>,
+    pub auth_disabled: bool,
+    pub jwt: Option<JwtConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    #[cfg(feature = "relayer")]
+    pub relayer: RelayerConfig,
+    pub voucher: VoucherConfig,
+    pub balance: BalanceConfig,
+}
+
+/// Problems found while reading the configuration, so they can be reported together.
+#[derive(Debug, Default)]
+pub struct ConfigErrors(Vec<String>);
+
+impl ConfigErrors {
+    /// Records `error` once, as settings shared by several chains are read for each of them.
+    pub fn push(&mut self, error: String) {
+        if !self.0.contains(&error) {
+            self.0.push(error);
+        }
+    }
+
+    /// `name` parsed as `T`, or `default` when it is not set. A value that doesn't parse is recorded.
+    pub fn parse<T: FromStr>(&mut self, name: &str, default: T) -> T {
+        self.optional(name).unwrap_or(default)
+    }
+
+    /// `name` parsed as `T`, or `None` when it is not set. A value that doesn't parse is recorded.
+    pub fn optional<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        match non_empty(name) {
+            Some(var) => match var.trim().parse::<T>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    self.push(format!("{} has an invalid value \"{}\"", name, var));
+                    None
+                }
+            },
+            None => None
+        }
+    }
+
+    /// `name`, recorded as missing when it is not set.
+    pub fn required(&mut self, name: &str) -> String {
+        match non_empty(name) {
+            Some(var) => var,
+            None => {
+                self.push(format!("{} is not set", name));
+                String::new()
+            }
+        }
+    }
+
+    /// `name` parsed like `parse`, recording values below `min` (or that don't compare, like NaN).
+    pub fn at_least<T: FromStr + PartialOrd + Display + Copy>(&mut self, name: &str, default: T, min: T) -> T {
+        let value = self.parse(name, default);
+
+        if let Some(Ordering::Less) | None = value.partial_cmp(&min) {
+            self.push(format!("{} must be at least {}", name, min));
+        }
+
+        value
+    }
+
+    /// `name` parsed like `parse`, recording values that are not above `bound`.
+    pub fn above<T: FromStr + PartialOrd + Display + Copy>(&mut self, name: &str, default: T, bound: T) -> T {
+        let value = self.parse(name, default);
+
+        if let Some(Ordering::Less | Ordering::Equal) | None = value.partial_cmp(&bound) {
+            self.push(format!("{} must be greater than {}", name, bound));
+        }
+
+        value
+    }
+
+    pub fn into_result<T>(self, value: impl FnOnce() -> T) -> Result<T, Vec<String>> {
+        match self.0.is_empty() {
+            true => Ok(value()),
+            false => Err(self.0)
+        }
+    }
+}
+
+/// `name`, or `None` when it is unset or empty. Every setting is read through this, as a copied
+/// `.env.example` leaves most variables empty and those mean "use the default".
+pub fn non_empty(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+/// `name` under a named prefix, e.g. `MINTER_PRIVATE_KEY` for the `minter` account.
+pub fn prefixed(prefix: &str, name: &str) -> String {
+    match prefix.is_empty() {
+        true => name.to_string(),
+        false => format!("{}_{}", prefix, name)
+    }
+}
+
+fn setting_name(prefix: &str, key: &str) -> String {
+    let key = key.replace('-', "_").to_uppercase();
+
+    let name = match prefix.is_empty() {
+        true => key,
+        false => format!("{}_{}", prefix, key)
+    };
+
+    match ALIASES.iter().find(|(alias, _)| name.starts_with(alias)) {
+        Some((alias, variable)) => name.replacen(alias, variable, 1),
+        None => name
+    }
+}
+
+fn setting_value(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text.to_owned()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        Value::Array(items) => items.iter().map(|item| setting_value(name, item)).collect::<Result<Vec<String>, String>>().map(|items| items.join(",")),
+        _ => Err(format!("{} must be a string, number, boolean or list", name))
+    }
+}
+
+/// Flattens sections into variable names: `[moralis] api_key` becomes `MORALIS_API_KEY`.
+fn flatten(prefix: &str, table: &serde_json::Map<String, Value>, settings: &mut Vec<(String, String)>, errors: &mut ConfigErrors) {
+    for (key, value) in table {
+        let name = setting_name(prefix, key);
+
+        match value {
+            Value::Object(section) => flatten(&name, section, settings, errors),
+            Value::Null => (),
+            value => match setting_value(&name, value) {
+                Ok(value) => settings.push((name, value)),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+}
+
+/// Reads a TOML file, or YAML for `.yaml`/`.yml`, into the environment. Like `.env`, variables that are
+/// already set win, so the layers are: defaults, then the file, then the environment. Empty variables, as
+/// left by a copied `.env.example`, count as unset.
+pub fn load_file(path: &str, errors: &mut ConfigErrors) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => return errors.push(format!("reading config file {} failed: {}", path, error)),
+    };
+
+    let parsed: Result<Value, String> = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&contents).map_err(|error| error.to_string()),
+        _ => toml::from_str(&contents).map_err(|error| error.to_string()),
+    };
+
+    let table = match parsed {
+        Ok(Value::Object(table)) => table,
+        Ok(_) => return errors.push(format!("config file {} must hold a table of settings", path)),
+        Err(error) => return errors.push(format!("config file {} is invalid: {}", path, error)),
+    };
+
+    let mut settings: Vec<(String, String)> = Vec::new();
+
+    flatten("", &table, &mut settings, errors);
+
+    for (name, value) in settings {
+        if non_empty(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+}
+
+/// Loads the configuration from `file` (or `CONFIG_FILE`) and the environment, returning every problem found.
+pub fn load(file: Option<String>) -> Result<Config, Vec<String>> {
+    let mut errors = ConfigErrors::default();
+
+    if let Some(file) = file.or_else(|| non_empty("CONFIG_FILE")) {
+        load_file(&file, &mut errors);
+    }
+
+    let balance = init_balance(&mut errors);
+
+    let accounts = init_accounts(balance.refuse_writes, &mut errors);
+
+    let api_keys = load_api_keys(non_empty("API_KEYS_FILE"), non_empty("API_KEYS"))
+        .map_err(|error| errors.push(format!("invalid API keys: {}", error)))
+        .ok();
+
+    let listen_url = non_empty("LISTEN_URL").unwrap_or_else(|| "0.0.0.0:8000".to_string());
+    let chains = init_chains(&mut errors);
+
+    #[cfg(feature = "moralis")]
+    let moralis = init_moralis();
+
+    let webhook_secret = non_empty("WEBHOOK_SECRET");
+    let webhook_max_retries = errors.parse("WEBHOOK_MAX_RETRIES", 5);
+    let event_poll_interval = errors.parse("EVENT_POLL_INTERVAL", 15);
+    let event_confirmations = errors.parse("EVENT_CONFIRMATIONS", 12);
+    let json_limit = errors.parse("JSON_PAYLOAD_LIMIT", 65536);
+    let batch = init_batch(&mut errors);
+    let airdrop = init_airdrop(&mut errors);
+    let auth_disabled = errors.parse("AUTH_DISABLED", false);
+    let jwt = init_jwt(&mut errors);
+    let rate_limit = init_rate_limit(&mut errors);
+    #[cfg(feature = "relayer")]
+    let relayer = init_relayer(&mut errors);
+    let voucher = init_voucher(&mut errors);
+
+    for error in accounts.unknown_chains(&chains) {
+        errors.push(error);
+    }
+
+    errors.into_result(|| Config {
+        listen_url,
+        accounts,
+        chains,
+        #[cfg(feature = "moralis")]
+        moralis,
+        webhook_secret,
+        webhook_max_retries,
+        event_poll_interval,
+        event_confirmations,
+        json_limit,
+        batch,
+        airdrop,
+        api_keys: api_keys.unwrap(),
+        auth_disabled,
+        jwt,
+        rate_limit,
+        #[cfg(feature = "relayer")]
+        relayer,
+        voucher,
+        balance,
+    })
+}
+
+/// Prints every problem found by `load`.
+pub fn report(errors: &[String]) {
+    eprintln!("Invalid configuration:");
+    for error in errors {
+        eprintln!("  - {}", error);
+    }
+}
+
+/// `load`, exiting with every problem listed when the configuration is invalid.
+pub fn init(file: Option<String>) -> Config {
+    match load(file) {
+        Ok(config) => config,
+        Err(errors) => {
+            report(&errors);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_checks_record_values_out_of_range() {
+        env::set_var("CONFIG_TEST_ZERO", "0");
+        env::set_var("CONFIG_TEST_NAN", "NaN");
+
+        let mut errors = ConfigErrors::default();
+
+        assert_eq!(errors.at_least::<usize>("CONFIG_TEST_ZERO", 100, 1), 0);
+        assert_eq!(errors.at_least::<usize>("CONFIG_TEST_UNSET", 100, 1), 100);
+        errors.above::<f64>("CONFIG_TEST_ZERO", 5.0, 0.0);
+        errors.above::<f64>("CONFIG_TEST_NAN", 5.0, 0.0);
+
+        assert_eq!(errors.into_result(|| ()).unwrap_err(), [
+            "CONFIG_TEST_ZERO must be at least 1",
+            "CONFIG_TEST_ZERO must be greater than 0",
+            "CONFIG_TEST_NAN must be greater than 0",
+        ]);
+    }
+
+    #[test]
+    fn empty_variables_count_as_unset() {
+        env::set_var("CONFIG_TEST_EMPTY", "");
+
+        let mut errors = ConfigErrors::default();
+
+        assert_eq!(non_empty("CONFIG_TEST_EMPTY"), None);
+        assert_eq!(errors.parse::<u64>("CONFIG_TEST_EMPTY", 5), 5);
+        assert_eq!(errors.optional::<u64>("CONFIG_TEST_EMPTY"), None);
+        errors.required("CONFIG_TEST_EMPTY");
+
+        assert_eq!(errors.into_result(|| ()).unwrap_err(), ["CONFIG_TEST_EMPTY is not set"]);
     }
 }
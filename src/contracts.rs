@@ -0,0 +1,433 @@
+//! Contract bindings. With the default `codegen` feature they are generated by `build.rs` from the
+//! artifacts in `CONTRACTS_DIR`; without it the ABIs are loaded at startup and called dynamically through
+//! types with the same names and methods, so a build needs no artifacts.
+
+use crate::{error::ApiError, config::{ConfigErrors, prefixed}, chain::ChainConfig};
+#[cfg(not(feature = "codegen"))]
+use std::{fs, path::PathBuf};
+#[cfg(not(feature = "codegen"))]
+use crate::config::non_empty;
+#[cfg(not(feature = "codegen"))]
+use crate::artifacts::{ArtifactFormat, detect, load_artifact, normalize};
+use ethcontract::prelude::*;
+
+#[cfg(feature = "codegen")]
+include!(concat!(env!("OUT_DIR"), "/SugarFungeAsset.rs"));
+#[cfg(all(feature = "codegen", feature = "wrapper"))]
+include!(concat!(env!("OUT_DIR"), "/Wrapped1155Factory.rs"));
+#[cfg(all(feature = "codegen", feature = "relayer"))]
+include!(concat!(env!("OUT_DIR"), "/MinimalForwarder.rs"));
+
+#[cfg(not(feature = "codegen"))]
+pub use self::dynamic::{sugar_funge_asset, SugarFungeAsset};
+#[cfg(all(not(feature = "codegen"), feature = "relayer"))]
+pub use self::dynamic::{minimal_forwarder, MinimalForwarder};
+#[cfg(all(not(feature = "codegen"), feature = "wrapper"))]
+pub use self::dynamic::{wrapped_1155_factory, Wrapped1155Factory};
+
+/// Where a contract is deployed and, without `codegen`, the ABI it is called through.
+#[derive(Clone, Debug)]
+pub struct ContractConfig {
+    pub address: Option<H160>,
+    #[cfg(not(feature = "codegen"))]
+    pub abi: ethcontract::common::Contract,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContractsConfig {
+    pub asset: ContractConfig,
+    #[cfg(feature = "relayer")]
+    pub forwarder: ContractConfig,
+    #[cfg(feature = "wrapper")]
+    pub wrapper_factory: ContractConfig,
+}
+
+/// The deployment on `chain_id`. Address overrides are read under the chain's `prefix`, e.g.
+/// `POLYGON_CONTRACTS_ASSET_ADDRESS`; the ABIs are the same on every chain.
+pub fn init_contracts(prefix: &str, chain_id: u64, errors: &mut ConfigErrors) -> ContractsConfig {
+    let project = Project::init(prefix, chain_id, errors);
+
+    ContractsConfig {
+        asset: project.contract("ASSET", "SugarFungeAsset", "SugarFunge/SugarFungeAsset.json", errors),
+        #[cfg(feature = "relayer")]
+        forwarder: project.contract("FORWARDER", "MinimalForwarder", "Forwarder/MinimalForwarder.json", errors),
+        #[cfg(feature = "wrapper")]
+        wrapper_factory: project.contract("WRAPPER_FACTORY", "Wrapped1155Factory", "ErcWrapper/Wrapped1155Factory.json", errors),
+    }
+}
+
+/// With `codegen` the ABIs were read by `build.rs`, so only the address overrides are left.
+#[cfg(feature = "codegen")]
+struct Project {
+    prefix: String,
+}
+
+#[cfg(feature = "codegen")]
+impl Project {
+    fn init(prefix: &str, _chain_id: u64, _errors: &mut ConfigErrors) -> Self {
+        Project { prefix: prefix.to_string() }
+    }
+
+    fn contract(&self, key: &str, _name: &str, _truffle_path: &str, errors: &mut ConfigErrors) -> ContractConfig {
+        ContractConfig {
+            address: errors.optional(&prefixed(&self.prefix, &format!("CONTRACTS_{}_ADDRESS", key))),
+        }
+    }
+}
+
+/// The Truffle, Hardhat or Foundry project at `CONTRACTS_DIR` the ABIs are read from at startup.
+#[cfg(not(feature = "codegen"))]
+struct Project {
+    prefix: String,
+    root: PathBuf,
+    format: ArtifactFormat,
+    chain_id: u64,
+}
+
+#[cfg(not(feature = "codegen"))]
+impl Project {
+    fn init(prefix: &str, chain_id: u64, errors: &mut ConfigErrors) -> Self {
+        let root = PathBuf::from(non_empty("CONTRACTS_DIR").unwrap_or_else(|| "contracts".to_string()));
+
+        let format = match non_empty("CONTRACTS_FORMAT") {
+            Some(format) => format.parse::<ArtifactFormat>().unwrap_or_else(|error| {
+                errors.push(format!("CONTRACTS_FORMAT: {}", error));
+                detect(&root)
+            }),
+            None => detect(&root),
+        };
+
+        Project { prefix: prefix.to_string(), root, format, chain_id }
+    }
+
+    /// Loads `CONTRACTS_<KEY>_ABI` when set, an artifact in any format or a plain ABI list, and otherwise
+    /// the project's artifact. `<PREFIX>_CONTRACTS_<KEY>_ADDRESS` wins over the deployment for `chain_id`.
+    fn contract(&self, key: &str, name: &str, truffle_path: &str, errors: &mut ConfigErrors) -> ContractConfig {
+        let artifact = match non_empty(&format!("CONTRACTS_{}_ABI", key)) {
+            Some(path) => fs::read_to_string(&path)
+                .map_err(|error| format!("reading the {} ABI {} failed: {}", name, path, error))
+                .and_then(|contents| serde_json::from_str(&contents).map_err(|error| format!("the {} ABI {} is invalid: {}", name, path, error)))
+                .and_then(|artifact| normalize(name, artifact, None)),
+            None => load_artifact(&self.root, self.format, name, truffle_path),
+        };
+
+        let abi = match artifact.and_then(|artifact| dynamic::load_abi(name, artifact)) {
+            Ok(abi) => abi,
+            Err(error) => {
+                errors.push(error);
+                ethcontract::common::Contract::with_name(name)
+            }
+        };
+
+        let address = errors.optional(&prefixed(&self.prefix, &format!("CONTRACTS_{}_ADDRESS", key)))
+            .or_else(|| abi.networks.get(&self.chain_id.to_string()).map(|network| network.address));
+
+        ContractConfig { address, abi }
+    }
+}
+
+/// The asset contract on `chain`, at its configured address or where its artifact says it is deployed.
+#[cfg(feature = "codegen")]
+pub async fn asset_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<SugarFungeAsset, ApiError> {
+    Ok(match chain.contracts.asset.address {
+        Some(address) => SugarFungeAsset::at(web3, address),
+        None => SugarFungeAsset::deployed(web3).await?,
+    })
+}
+
+#[cfg(all(feature = "codegen", feature = "relayer"))]
+pub async fn forwarder_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<MinimalForwarder, ApiError> {
+    Ok(match chain.contracts.forwarder.address {
+        Some(address) => MinimalForwarder::at(web3, address),
+        None => MinimalForwarder::deployed(web3).await?,
+    })
+}
+
+#[cfg(all(feature = "codegen", feature = "wrapper"))]
+pub async fn wrapper_factory_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<Wrapped1155Factory, ApiError> {
+    Ok(match chain.contracts.wrapper_factory.address {
+        Some(address) => Wrapped1155Factory::at(web3, address),
+        None => Wrapped1155Factory::deployed(web3).await?,
+    })
+}
+
+#[cfg(not(feature = "codegen"))]
+pub async fn asset_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<SugarFungeAsset, ApiError> {
+    Ok(SugarFungeAsset::new(dynamic::instance(web3, &chain.contracts.asset)?))
+}
+
+#[cfg(all(not(feature = "codegen"), feature = "relayer"))]
+pub async fn forwarder_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<MinimalForwarder, ApiError> {
+    Ok(MinimalForwarder::new(dynamic::instance(web3, &chain.contracts.forwarder)?))
+}
+
+#[cfg(all(not(feature = "codegen"), feature = "wrapper"))]
+pub async fn wrapper_factory_contract(chain: &ChainConfig, web3: &Web3<Http>) -> Result<Wrapped1155Factory, ApiError> {
+    Ok(Wrapped1155Factory::new(dynamic::instance(web3, &chain.contracts.wrapper_factory)?))
+}
+
+/// Hand-written counterparts of the generated bindings, covering the calls and events the service uses.
+#[cfg(not(feature = "codegen"))]
+mod dynamic {
+    use super::ContractConfig;
+    use crate::error::ApiError;
+    use ethcontract::{prelude::*, common::{Contract, artifact::truffle::TruffleLoader}, contract::{EventBuilder, MethodBuilder}, tokens::Tokenize};
+    #[cfg(any(feature = "relayer", feature = "wrapper"))]
+    use ethcontract::contract::ViewMethodBuilder;
+    use serde_json::Value;
+
+    /// Functions and events of each contract the service calls, checked when its ABI is loaded.
+    const REQUIRED: &[(&str, &[&str], &[&str])] = &[
+        ("SugarFungeAsset", &["mint", "safeTransferFrom", "safeBatchTransferFrom"], &["TransferSingle", "TransferBatch"]),
+        ("MinimalForwarder", &["getNonce", "verify", "execute"], &[]),
+        ("Wrapped1155Factory", &["unwrap", "getWrapped1155"], &["Wrapped1155Creation"]),
+    ];
+
+    /// Reads a normalized artifact, checking it has everything the service calls.
+    pub fn load_abi(name: &str, artifact: Value) -> Result<Contract, String> {
+        let abi = TruffleLoader::new()
+            .load_contract_from_str(&artifact.to_string())
+            .map_err(|error| format!("the {} ABI is invalid: {}", name, error))?;
+
+        if let Some((_, functions, events)) = REQUIRED.iter().find(|(contract, _, _)| *contract == name) {
+            if let Some(function) = functions.iter().find(|function| abi.abi.function(function).is_err()) {
+                return Err(format!("the {} ABI has no {} function", name, function));
+            }
+            if let Some(event) = events.iter().find(|event| abi.abi.event(event).is_err()) {
+                return Err(format!("the {} ABI has no {} event", name, event));
+            }
+        }
+
+        Ok(abi)
+    }
+
+    pub fn instance(web3: &Web3<Http>, contract: &ContractConfig) -> Result<Instance<Http>, ApiError> {
+        match contract.address {
+            Some(address) => Ok(Instance::at(web3.clone(), contract.abi.clone(), address)),
+            None => Err(ApiError::NotConfigured(format!("The {} address", contract.abi.name))),
+        }
+    }
+
+    fn method<P: Tokenize, R: Tokenize>(instance: &Instance<Http>, name: &str, params: P) -> MethodBuilder<Http, R> {
+        let selector = instance.abi().function(name).expect("checked when the ABI was loaded").short_signature();
+
+        instance.method(selector, params).expect("checked when the ABI was loaded")
+    }
+
+    #[cfg(any(feature = "relayer", feature = "wrapper"))]
+    fn view_method<P: Tokenize, R: Tokenize>(instance: &Instance<Http>, name: &str, params: P) -> ViewMethodBuilder<Http, R> {
+        let selector = instance.abi().function(name).expect("checked when the ABI was loaded").short_signature();
+
+        instance.view_method(selector, params).expect("checked when the ABI was loaded")
+    }
+
+    fn event<E: Tokenize>(instance: &Instance<Http>, name: &str) -> EventBuilder<Http, E> {
+        let signature = instance.abi().event(name).expect("checked when the ABI was loaded").signature();
+
+        instance.event(signature).expect("checked when the ABI was loaded")
+    }
+
+    pub use self::sugar_funge_asset::Contract as SugarFungeAsset;
+    #[cfg(feature = "relayer")]
+    pub use self::minimal_forwarder::Contract as MinimalForwarder;
+    #[cfg(feature = "wrapper")]
+    pub use self::wrapped_1155_factory::Contract as Wrapped1155Factory;
+
+    pub mod sugar_funge_asset {
+        use super::{method, event};
+        use ethcontract::{prelude::*, contract::{EventBuilder, MethodBuilder}};
+
+        #[derive(Clone)]
+        pub struct Contract {
+            instance: Instance<Http>,
+        }
+
+        impl Contract {
+            pub fn new(instance: Instance<Http>) -> Self {
+                Contract { instance }
+            }
+
+            pub fn address(&self) -> H160 {
+                self.instance.address()
+            }
+
+            pub fn mint(&self, account: H160, id: U256, amount: U256, data: Bytes<Vec<u8>>) -> MethodBuilder<Http, ()> {
+                method(&self.instance, "mint", (account, id, amount, data))
+            }
+
+            pub fn safe_transfer_from(&self, from: H160, to: H160, id: U256, amount: U256, data: Bytes<Vec<u8>>) -> MethodBuilder<Http, ()> {
+                method(&self.instance, "safeTransferFrom", (from, to, id, amount, data))
+            }
+
+            pub fn safe_batch_transfer_from(&self, from: H160, to: H160, ids: Vec<U256>, amounts: Vec<U256>, data: Bytes<Vec<u8>>) -> MethodBuilder<Http, ()> {
+                method(&self.instance, "safeBatchTransferFrom", (from, to, ids, amounts, data))
+            }
+
+            pub fn events(&self) -> Events<'_> {
+                Events { instance: &self.instance }
+            }
+        }
+
+        pub struct Events<'a> {
+            instance: &'a Instance<Http>,
+        }
+
+        impl Events<'_> {
+            pub fn transfer_single(&self) -> EventBuilder<Http, event_data::TransferSingle> {
+                event(self.instance, "TransferSingle")
+            }
+
+            pub fn transfer_batch(&self) -> EventBuilder<Http, event_data::TransferBatch> {
+                event(self.instance, "TransferBatch")
+            }
+        }
+
+        pub mod event_data {
+            use ethcontract::{prelude::*, tokens::{Error, Tokenize}, web3::ethabi::Token};
+
+            #[derive(Clone, Debug)]
+            pub struct TransferSingle {
+                pub operator: H160,
+                pub from: H160,
+                pub to: H160,
+                pub id: U256,
+                pub value: U256,
+            }
+
+            impl Tokenize for TransferSingle {
+                fn from_token(token: Token) -> Result<Self, Error> {
+                    let (operator, from, to, id, value) = Tokenize::from_token(token)?;
+
+                    Ok(TransferSingle { operator, from, to, id, value })
+                }
+
+                fn into_token(self) -> Token {
+                    (self.operator, self.from, self.to, self.id, self.value).into_token()
+                }
+            }
+
+            #[derive(Clone, Debug)]
+            pub struct TransferBatch {
+                pub operator: H160,
+                pub from: H160,
+                pub to: H160,
+                pub ids: Vec<U256>,
+                pub values: Vec<U256>,
+            }
+
+            impl Tokenize for TransferBatch {
+                fn from_token(token: Token) -> Result<Self, Error> {
+                    let (operator, from, to, ids, values) = Tokenize::from_token(token)?;
+
+                    Ok(TransferBatch { operator, from, to, ids, values })
+                }
+
+                fn into_token(self) -> Token {
+                    (self.operator, self.from, self.to, self.ids, self.values).into_token()
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "relayer")]
+    pub mod minimal_forwarder {
+        use super::{method, view_method};
+        use ethcontract::{prelude::*, contract::{MethodBuilder, ViewMethodBuilder}};
+
+        /// `ForwardRequest(from, to, value, gas, nonce, data)`.
+        type Request = (H160, H160, U256, U256, U256, Bytes<Vec<u8>>);
+
+        #[derive(Clone)]
+        pub struct Contract {
+            instance: Instance<Http>,
+        }
+
+        impl Contract {
+            pub fn new(instance: Instance<Http>) -> Self {
+                Contract { instance }
+            }
+
+            pub fn address(&self) -> H160 {
+                self.instance.address()
+            }
+
+            pub fn get_nonce(&self, from: H160) -> ViewMethodBuilder<Http, U256> {
+                view_method(&self.instance, "getNonce", (from,))
+            }
+
+            pub fn verify(&self, request: Request, signature: Bytes<Vec<u8>>) -> ViewMethodBuilder<Http, bool> {
+                view_method(&self.instance, "verify", (request, signature))
+            }
+
+            pub fn execute(&self, request: Request, signature: Bytes<Vec<u8>>) -> MethodBuilder<Http, (bool, Bytes<Vec<u8>>)> {
+                method(&self.instance, "execute", (request, signature))
+            }
+        }
+    }
+
+    #[cfg(feature = "wrapper")]
+    pub mod wrapped_1155_factory {
+        use super::{method, view_method, event};
+        use ethcontract::{prelude::*, contract::{EventBuilder, MethodBuilder, ViewMethodBuilder}};
+
+        #[derive(Clone)]
+        pub struct Contract {
+            instance: Instance<Http>,
+        }
+
+        impl Contract {
+            pub fn new(instance: Instance<Http>) -> Self {
+                Contract { instance }
+            }
+
+            pub fn address(&self) -> H160 {
+                self.instance.address()
+            }
+
+            pub fn unwrap(&self, multi_token: H160, token_id: U256, amount: U256, recipient: H160, data: Bytes<Vec<u8>>) -> MethodBuilder<Http, ()> {
+                method(&self.instance, "unwrap", (multi_token, token_id, amount, recipient, data))
+            }
+
+            pub fn get_wrapped_1155(&self, multi_token: H160, token_id: U256, data: Bytes<Vec<u8>>) -> ViewMethodBuilder<Http, H160> {
+                view_method(&self.instance, "getWrapped1155", (multi_token, token_id, data))
+            }
+
+            pub fn events(&self) -> Events<'_> {
+                Events { instance: &self.instance }
+            }
+        }
+
+        pub struct Events<'a> {
+            instance: &'a Instance<Http>,
+        }
+
+        impl Events<'_> {
+            pub fn wrapped_1155_creation(&self) -> EventBuilder<Http, event_data::Wrapped1155Creation> {
+                event(self.instance, "Wrapped1155Creation")
+            }
+        }
+
+        pub mod event_data {
+            use ethcontract::{prelude::*, tokens::{Error, Tokenize}, web3::ethabi::Token};
+
+            #[derive(Clone, Debug)]
+            pub struct Wrapped1155Creation {
+                pub multi_token: H160,
+                pub token_id: U256,
+                pub wrapped_token: H160,
+            }
+
+            impl Tokenize for Wrapped1155Creation {
+                fn from_token(token: Token) -> Result<Self, Error> {
+                    let (multi_token, token_id, wrapped_token) = Tokenize::from_token(token)?;
+
+                    Ok(Wrapped1155Creation { multi_token, token_id, wrapped_token })
+                }
+
+                fn into_token(self) -> Token {
+                    (self.multi_token, self.token_id, self.wrapped_token).into_token()
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::ApiError;
+use std::str::FromStr;
+use ethcontract::{prelude::*, web3::{ethabi::{encode, Token}, signing::{keccak256, recover}}};
+use serde_json::{json, Value};
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// EIP-712 signing domain of a contract.
+#[derive(Debug, Clone)]
+pub struct Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: H160,
+}
+
+impl Domain {
+    pub fn separator(&self) -> [u8; 32] {
+        keccak256(&encode(&[
+            Token::FixedBytes(keccak256(DOMAIN_TYPE.as_bytes()).to_vec()),
+            Token::FixedBytes(keccak256(self.name.as_bytes()).to_vec()),
+            Token::FixedBytes(keccak256(self.version.as_bytes()).to_vec()),
+            Token::Uint(self.chain_id.into()),
+            Token::Address(self.verifying_contract),
+        ]))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "version": self.version,
+            "chainId": self.chain_id,
+            "verifyingContract": format!("0x{:x}", self.verifying_contract)
+        })
+    }
+
+    pub fn type_json() -> Value {
+        json!([
+            { "name": "name", "type": "string" },
+            { "name": "version", "type": "string" },
+            { "name": "chainId", "type": "uint256" },
+            { "name": "verifyingContract", "type": "address" }
+        ])
+    }
+}
+
+pub fn type_hash(signature: &str) -> Token {
+    Token::FixedBytes(keccak256(signature.as_bytes()).to_vec())
+}
+
+pub fn bytes_hash(bytes: &[u8]) -> Token {
+    Token::FixedBytes(keccak256(bytes).to_vec())
+}
+
+pub fn struct_hash(tokens: &[Token]) -> [u8; 32] {
+    keccak256(&encode(tokens))
+}
+
+/// The digest a wallet signs for `eth_signTypedData_v4`.
+pub fn typed_data_hash(domain: &Domain, struct_hash: [u8; 32]) -> [u8; 32] {
+    let mut message: Vec<u8> = [0x19, 0x01].to_vec();
+    message.extend_from_slice(&domain.separator());
+    message.extend_from_slice(&struct_hash);
+
+    keccak256(&message)
+}
+
+pub fn parse_signature(signature: &str) -> Result<Vec<u8>, ApiError> {
+    let signature = hex::decode(signature.trim_start_matches("0x"))
+        .map_err(|_| ApiError::InvalidSignature("signature is not hex encoded".to_string()))?;
+
+    match signature.len() {
+        65 => Ok(signature),
+        _ => Err(ApiError::InvalidSignature("signature must be 65 bytes".to_string()))
+    }
+}
+
+pub fn recover_signer(digest: [u8; 32], signature: &[u8]) -> Result<H160, ApiError> {
+    let recovery_id = match signature[64] {
+        v @ 27..=28 => v - 27,
+        v @ 0..=1 => v,
+        _ => return Err(ApiError::InvalidSignature("invalid recovery id".to_string())),
+    };
+
+    recover(&digest, &signature[..64], recovery_id as i32)
+        .map_err(|_| ApiError::InvalidSignature("signature recovery failed".to_string()))
+}
+
+pub fn parse_address(name: &str, address: &str) -> Result<H160, ApiError> {
+    H160::from_str(address).map_err(|_| ApiError::InvalidRequest(format!("{} is not a valid address", name)))
+}
+
+/// Accepts decimal or `0x` prefixed hex numbers, as wallets send both.
+pub fn parse_u256(name: &str, number: &str) -> Result<U256, ApiError> {
+    let parsed = match number.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(number).ok(),
+    };
+
+    parsed.ok_or_else(|| ApiError::InvalidRequest(format!("{} is not a valid number", name)))
+}
+
+pub fn parse_bytes(name: &str, bytes: &str) -> Result<Vec<u8>, ApiError> {
+    hex::decode(bytes.trim_start_matches("0x")).map_err(|_| ApiError::InvalidRequest(format!("{} is not hex encoded", name)))
+}
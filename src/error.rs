@@ -1,7 +1,11 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::{http::{header::RETRY_AFTER, StatusCode}, HttpResponse, ResponseError};
 use derive_more::Error;
-use ethcontract::errors::{DeployError, MethodError};
-use serde::Serialize;
+use ethcontract::errors::{DeployError, ExecutionError, MethodError};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+use crate::{auth::Scope, batch::BatchIssue};
 
 #[derive(Debug, Error)] 
 pub enum ApiError {
@@ -9,7 +13,27 @@ pub enum ApiError {
     SerdeError(serde_json::Error),
     ContractError(DeployError),
     MethodError(MethodError),
-    TransportError
+    ExecutionError(ExecutionError),
+    TransportError,
+    WebhookNotFound(#[error(not(source))] u64),
+    InvalidWebhook(#[error(not(source))] String),
+    Unauthorized,
+    InvalidToken(#[error(not(source))] String),
+    Forbidden(#[error(not(source))] Scope),
+    RateLimited(#[error(not(source))] u64),
+    SignerError(#[error(not(source))] String),
+    UnknownSigner(#[error(not(source))] String),
+    SignerNotAllowed(#[error(not(source))] String),
+    InvalidSignature(#[error(not(source))] String),
+    InvalidRequest(#[error(not(source))] String),
+    InsufficientFunds(#[error(not(source))] String),
+    ValidationError(ValidationErrors),
+    PayloadTooLarge(#[error(not(source))] usize),
+    InvalidBatch(#[error(not(source))] Vec<BatchIssue>),
+    JobNotFound(#[error(not(source))] u64),
+    NotConfigured(#[error(not(source))] String),
+    UnknownChain(#[error(not(source))] String),
+    SignerChainMismatch(#[error(not(source))] String, #[error(not(source))] String)
 }
 
 impl std::fmt::Display for ApiError {
@@ -19,7 +43,27 @@ impl std::fmt::Display for ApiError {
             Self::SerdeError(error) => write!(f, "Error when parsing JSON: {}", error.to_string()),
             Self::ContractError(error) => write!(f, "Locating deployed contract failed: {}", error.to_string()),
             Self::MethodError(error) => write!(f, "Contract method failed: {}", error.to_string()),
-            Self::TransportError => write!(f, "Create transport failed")
+            Self::ExecutionError(error) => write!(f, "Contract query failed: {}", error.to_string()),
+            Self::TransportError => write!(f, "Create transport failed"),
+            Self::WebhookNotFound(id) => write!(f, "Webhook subscription {} not found", id),
+            Self::InvalidWebhook(reason) => write!(f, "Invalid webhook subscription: {}", reason),
+            Self::Unauthorized => write!(f, "Missing or invalid credentials"),
+            Self::InvalidToken(reason) => write!(f, "Invalid bearer token: {}", reason),
+            Self::Forbidden(scope) => write!(f, "Credentials are missing the \"{}\" scope", scope.as_str()),
+            Self::RateLimited(retry_after) => write!(f, "Rate limit exceeded, retry in {} seconds", retry_after),
+            Self::SignerError(reason) => write!(f, "Signing transaction failed: {}", reason),
+            Self::UnknownSigner(name) => write!(f, "Signer \"{}\" is not configured", name),
+            Self::SignerNotAllowed(name) => write!(f, "Credentials may not use signer \"{}\"", name),
+            Self::InvalidSignature(reason) => write!(f, "Invalid signature: {}", reason),
+            Self::InvalidRequest(reason) => write!(f, "Invalid request: {}", reason),
+            Self::InsufficientFunds(address) => write!(f, "Signer {} balance is below the gas threshold", address),
+            Self::ValidationError(errors) => write!(f, "Request validation failed: {}", errors),
+            Self::PayloadTooLarge(limit) => write!(f, "Request body is larger than {} bytes", limit),
+            Self::InvalidBatch(issues) => write!(f, "Batch has {} invalid entries at indices {:?}", issues.len(), issues.iter().map(|issue| issue.index).collect::<Vec<usize>>()),
+            Self::JobNotFound(id) => write!(f, "Job {} not found", id),
+            Self::NotConfigured(feature) => write!(f, "{} is not configured on this server", feature),
+            Self::UnknownChain(chain) => write!(f, "Chain \"{}\" is not served by this server", chain),
+            Self::SignerChainMismatch(name, chain) => write!(f, "Signer \"{}\" does not sign on chain \"{}\"", name, chain)
         }
     }
 }
@@ -31,7 +75,36 @@ impl ApiError {
             Self::SerdeError(_) => "SerdeError".to_string(),
             Self::ContractError(_) => "ContractError".to_string(),
             Self::MethodError(_) => "MethodError".to_string(),
-            Self::TransportError => "TransportError".to_string()
+            Self::ExecutionError(_) => "ExecutionError".to_string(),
+            Self::TransportError => "TransportError".to_string(),
+            Self::WebhookNotFound(_) => "WebhookNotFound".to_string(),
+            Self::InvalidWebhook(_) => "InvalidWebhook".to_string(),
+            Self::Unauthorized => "Unauthorized".to_string(),
+            Self::InvalidToken(_) => "InvalidToken".to_string(),
+            Self::Forbidden(_) => "Forbidden".to_string(),
+            Self::RateLimited(_) => "RateLimited".to_string(),
+            Self::SignerError(_) => "SignerError".to_string(),
+            Self::UnknownSigner(_) => "UnknownSigner".to_string(),
+            Self::SignerNotAllowed(_) => "SignerNotAllowed".to_string(),
+            Self::InvalidSignature(_) => "InvalidSignature".to_string(),
+            Self::InvalidRequest(_) => "InvalidRequest".to_string(),
+            Self::InsufficientFunds(_) => "InsufficientFunds".to_string(),
+            Self::ValidationError(_) => "ValidationError".to_string(),
+            Self::PayloadTooLarge(_) => "PayloadTooLarge".to_string(),
+            Self::InvalidBatch(_) => "InvalidBatch".to_string(),
+            Self::JobNotFound(_) => "JobNotFound".to_string(),
+            Self::NotConfigured(_) => "NotConfigured".to_string(),
+            Self::UnknownChain(_) => "UnknownChain".to_string(),
+            Self::SignerChainMismatch(_, _) => "SignerChainMismatch".to_string()
+        }
+    }
+
+    /// Structured detail for errors that concern specific fields or batch entries.
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            Self::ValidationError(errors) => serde_json::to_value(errors).ok(),
+            Self::InvalidBatch(issues) => serde_json::to_value(issues).ok(),
+            _ => None
         }
     }
 }
@@ -42,7 +115,27 @@ impl ResponseError for ApiError {
             Self::SerdeError(_) => StatusCode::BAD_REQUEST,
             Self::ContractError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::MethodError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::ExecutionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::TransportError => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::WebhookNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidWebhook(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::SignerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UnknownSigner(_) => StatusCode::BAD_REQUEST,
+            Self::SignerNotAllowed(_) => StatusCode::FORBIDDEN,
+            Self::InvalidSignature(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            Self::InsufficientFunds(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::InvalidBatch(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::JobNotFound(_) => StatusCode::NOT_FOUND,
+            Self::NotConfigured(_) => StatusCode::NOT_IMPLEMENTED,
+            Self::UnknownChain(_) => StatusCode::BAD_REQUEST,
+            Self::SignerChainMismatch(_, _) => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -52,8 +145,13 @@ impl ResponseError for ApiError {
             code: status_code.as_u16(),
             message: self.to_string(),
             error: self.name(),
+            details: self.details(),
         };
-        HttpResponse::build(status_code).json(error_response)
+        let mut response = HttpResponse::build(status_code);
+        if let Self::RateLimited(retry_after) = self {
+            response.insert_header((RETRY_AFTER, retry_after.to_string()));
+        }
+        response.json(error_response)
     }
 }
 
@@ -69,15 +167,27 @@ impl From<MethodError> for ApiError {
     }
 }
 
+impl From<ExecutionError> for ApiError {
+    fn from(error: ExecutionError) -> Self {
+        ApiError::ExecutionError(error)
+    }
+}
+
 impl From<serde_json::Error> for ApiError {
     fn from(error: serde_json::Error) -> Self {
         ApiError::SerdeError(error)
     }
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    code: u16,
-    error: String,
-    message: String,
+/// Body of every error answer; `error` is the `ApiError` variant name.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ErrorResponse {
+    #[schema(example = 422)]
+    pub code: u16,
+    #[schema(example = "ValidationError")]
+    pub error: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
 }
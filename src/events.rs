@@ -0,0 +1,309 @@
+use crate::{error::ApiError, config::Config, chain::ChainConfig, asset::get_web3, contracts::{asset_contract, sugar_funge_asset::event_data::{TransferSingle, TransferBatch}}, webhook::{WebhookStore, dispatch_event}, stream::{EventHub, TxState}};
+use std::time::Duration;
+use ethcontract::{prelude::*, BlockNumber, EventMetadata};
+use actix_web::{rt::time::sleep, web::Data};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use log::{error, info};
+#[cfg(feature = "wrapper")]
+use crate::contracts::{wrapper_factory_contract, wrapped_1155_factory::event_data::Wrapped1155Creation};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Transfer,
+    Mint,
+    Burn,
+    Wrap,
+    Unwrap,
+    WrapperCreated
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ContractEvent {
+    pub chain: String,
+    pub kind: EventKind,
+    pub event: String,
+    pub contract: String,
+    pub operator: String,
+    pub from: String,
+    pub to: String,
+    pub ids: Vec<String>,
+    pub amounts: Vec<String>,
+    pub tx: String,
+    pub block: u64,
+    pub log_index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrapped_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct EventFilter {
+    pub chain: Option<String>,
+    pub contract: Option<String>,
+    pub token_id: Option<u64>,
+    pub address: Option<String>,
+    pub kinds: Option<Vec<EventKind>>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &ContractEvent) -> bool {
+        let chain = match &self.chain {
+            Some(chain) => chain == &event.chain,
+            None => true,
+        };
+        let contract = match &self.contract {
+            Some(contract) => contract.eq_ignore_ascii_case(&event.contract),
+            None => true,
+        };
+        let token_id = match &self.token_id {
+            Some(id) => event.ids.contains(&id.to_string()),
+            None => true,
+        };
+        let address = match &self.address {
+            Some(address) => address.eq_ignore_ascii_case(&event.from) || address.eq_ignore_ascii_case(&event.to),
+            None => true,
+        };
+        let kind = match &self.kinds {
+            Some(kinds) => kinds.contains(&event.kind),
+            None => true,
+        };
+
+        chain && contract && token_id && address && kind
+    }
+}
+
+fn event_kind(from: H160, to: H160, factory: H160) -> EventKind {
+    if to == factory {
+        EventKind::Wrap
+    } else if from == factory {
+        EventKind::Unwrap
+    } else if from == H160::zero() {
+        EventKind::Mint
+    } else if to == H160::zero() {
+        EventKind::Burn
+    } else {
+        EventKind::Transfer
+    }
+}
+
+fn event_position(meta: &Option<EventMetadata>) -> (String, u64, usize) {
+    match meta {
+        Some(meta) => (format!("0x{:x}", meta.transaction_hash), meta.block_number, meta.log_index),
+        None => ("".to_string(), 0, 0),
+    }
+}
+
+/// The wrapper factory's address, which tells wraps and unwraps apart from transfers, and its
+/// `Wrapped1155Creation` events. Without a deployed factory the address is zero.
+#[cfg(feature = "wrapper")]
+async fn wrapper_events(chain: &ChainConfig, web3: &Web3<Http>, from_block: u64, to_block: u64) -> Result<(H160, Vec<ContractEvent>), ApiError> {
+
+    let mut events: Vec<ContractEvent> = [].to_vec();
+
+    let factory_contract = match wrapper_factory_contract(chain, web3).await {
+        Ok(factory_contract) => factory_contract,
+        Err(_) => return Ok((H160::zero(), events)),
+    };
+
+    let factory = factory_contract.address();
+
+    let creations = factory_contract.events().wrapped_1155_creation()
+        .from_block(BlockNumber::Number(from_block.into()))
+        .to_block(BlockNumber::Number(to_block.into()))
+        .query()
+        .await?;
+
+    for creation in creations {
+        let (tx, block, log_index) = event_position(&creation.meta);
+        if let Some(Wrapped1155Creation { multi_token, token_id, wrapped_token }) = creation.added() {
+            events.push(ContractEvent {
+                chain: chain.name.to_owned(),
+                kind: EventKind::WrapperCreated,
+                event: "Wrapped1155Creation".to_string(),
+                contract: format!("0x{:x}", factory),
+                operator: format!("0x{:x}", factory),
+                from: format!("0x{:x}", multi_token),
+                to: format!("0x{:x}", wrapped_token),
+                ids: [token_id.to_string()].to_vec(),
+                amounts: [].to_vec(),
+                tx,
+                block,
+                log_index,
+                wrapped_token: Some(format!("0x{:x}", wrapped_token)),
+            });
+        }
+    }
+
+    Ok((factory, events))
+}
+
+#[cfg(not(feature = "wrapper"))]
+async fn wrapper_events(_chain: &ChainConfig, _web3: &Web3<Http>, _from_block: u64, _to_block: u64) -> Result<(H160, Vec<ContractEvent>), ApiError> {
+    Ok((H160::zero(), [].to_vec()))
+}
+
+pub async fn get_contract_events(chain: &ChainConfig, from_block: u64, to_block: u64) -> Result<Vec<ContractEvent>, ApiError> {
+
+    let web3 = get_web3(chain)?;
+
+    let contract = asset_contract(chain, &web3).await?;
+
+    let (factory, mut events) = wrapper_events(chain, &web3, from_block, to_block).await?;
+
+    let contract_address = format!("0x{:x}", contract.address());
+
+    let singles = contract.events().transfer_single()
+        .from_block(BlockNumber::Number(from_block.into()))
+        .to_block(BlockNumber::Number(to_block.into()))
+        .query()
+        .await?;
+
+    for single in singles {
+        let (tx, block, log_index) = event_position(&single.meta);
+        if let Some(TransferSingle { operator, from, to, id, value }) = single.added() {
+            events.push(ContractEvent {
+                chain: chain.name.to_owned(),
+                kind: event_kind(from, to, factory),
+                event: "TransferSingle".to_string(),
+                contract: contract_address.to_owned(),
+                operator: format!("0x{:x}", operator),
+                from: format!("0x{:x}", from),
+                to: format!("0x{:x}", to),
+                ids: [id.to_string()].to_vec(),
+                amounts: [value.to_string()].to_vec(),
+                tx,
+                block,
+                log_index,
+                wrapped_token: None,
+            });
+        }
+    }
+
+    let batches = contract.events().transfer_batch()
+        .from_block(BlockNumber::Number(from_block.into()))
+        .to_block(BlockNumber::Number(to_block.into()))
+        .query()
+        .await?;
+
+    for batch in batches {
+        let (tx, block, log_index) = event_position(&batch.meta);
+        if let Some(TransferBatch { operator, from, to, ids, values }) = batch.added() {
+            events.push(ContractEvent {
+                chain: chain.name.to_owned(),
+                kind: event_kind(from, to, factory),
+                event: "TransferBatch".to_string(),
+                contract: contract_address.to_owned(),
+                operator: format!("0x{:x}", operator),
+                from: format!("0x{:x}", from),
+                to: format!("0x{:x}", to),
+                ids: ids.iter().map(|x| x.to_string()).collect(),
+                amounts: values.iter().map(|x| x.to_string()).collect(),
+                tx,
+                block,
+                log_index,
+                wrapped_token: None,
+            });
+        }
+    }
+
+    events.sort_by_key(|event| (event.block, event.log_index));
+
+    Ok(events)
+}
+
+async fn check_transactions(config: &Config, chain: &ChainConfig, hub: &EventHub, latest_block: u64) -> Result<(), ApiError> {
+
+    let web3 = get_web3(chain)?;
+
+    hub.expire_transactions(&chain.name);
+
+    for mut status in hub.pending_transactions(&chain.name) {
+        let receipt = match web3.eth().transaction_receipt(status.tx).await {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => continue,
+            Err(error) => {
+                error!("Fetching receipt for 0x{:x} failed: {}", status.tx, error);
+                continue;
+            }
+        };
+
+        let block = match receipt.block_number {
+            Some(block) => block.as_u64(),
+            None => continue,
+        };
+
+        let state = if receipt.status != Some(1.into()) {
+            TxState::Failed
+        } else if latest_block >= block + config.event_confirmations {
+            TxState::Confirmed
+        } else {
+            TxState::Mined
+        };
+
+        if state != status.state {
+            status.state = state;
+            status.block = Some(block);
+            hub.update_transaction(status);
+        }
+    }
+
+    Ok(())
+}
+
+/// Polls `chain` for confirmed contract events and transaction receipts, feeding webhooks and event streams.
+/// One watcher runs for every chain the service is configured for.
+pub async fn watch(config: Config, chain: ChainConfig, webhooks: Data<WebhookStore>, hub: Data<EventHub>) {
+
+    let mut last_block: Option<u64> = None;
+
+    loop {
+        sleep(Duration::from_secs(config.event_poll_interval)).await;
+
+        hub.keep_alive();
+
+        let latest_block = match get_web3(&chain) {
+            Ok(web3) => match web3.eth().block_number().await {
+                Ok(block) => block.as_u64(),
+                Err(error) => {
+                    error!("Fetching latest block of {} failed: {}", chain.name, error);
+                    continue;
+                }
+            },
+            Err(error) => {
+                error!("{}", error);
+                continue;
+            }
+        };
+
+        if let Err(error) = check_transactions(&config, &chain, &hub, latest_block).await {
+            error!("Checking submitted transactions on {} failed: {}", chain.name, error);
+        }
+
+        let confirmed_block = latest_block.saturating_sub(config.event_confirmations);
+
+        let from_block = match last_block {
+            Some(block) => block + 1,
+            None => {
+                info!("Watching contract events on {} from block {}", chain.name, confirmed_block);
+                last_block = Some(confirmed_block);
+                continue;
+            }
+        };
+
+        if from_block > confirmed_block {
+            continue;
+        }
+
+        match get_contract_events(&chain, from_block, confirmed_block).await {
+            Ok(events) => {
+                for event in events {
+                    dispatch_event(&webhooks, &config, &event);
+                    hub.publish_event(&event);
+                }
+                last_block = Some(confirmed_block);
+            },
+            Err(error) => error!("Fetching contract events on {} failed: {}", chain.name, error)
+        }
+    }
+}
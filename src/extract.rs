@@ -0,0 +1,64 @@
+use crate::{error::ApiError, config::Config, eip712::parse_address};
+use std::ops::Deref;
+use actix_web::{dev::Payload, error::JsonPayloadError, web::{Json, JsonConfig}, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError};
+
+/// A JSON request body that has been deserialized and passed its `Validate` rules.
+#[derive(Debug)]
+pub struct ValidJson<T>(pub T);
+
+impl<T> ValidJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+
+            value.validate().map_err(ApiError::ValidationError)?;
+
+            Ok(ValidJson(value))
+        })
+    }
+}
+
+/// `#[validate(custom = "validate_address")]` rule for fields holding a hex account address.
+pub fn validate_address(address: &str) -> Result<(), ValidationError> {
+    parse_address("address", address).map(|_| ()).map_err(|_| ValidationError::new("address"))
+}
+
+/// Body limit and `ErrorResponse` formatting for every JSON extractor.
+pub fn json_config(config: &Config) -> JsonConfig {
+    JsonConfig::default()
+        .limit(config.json_limit)
+        .error_handler(|error, _req| ApiError::from(error).into())
+}
+
+impl From<JsonPayloadError> for ApiError {
+    fn from(error: JsonPayloadError) -> Self {
+        match error {
+            JsonPayloadError::Deserialize(error) => ApiError::SerdeError(error),
+            JsonPayloadError::Overflow { limit } | JsonPayloadError::OverflowKnownLength { limit, .. } => ApiError::PayloadTooLarge(limit),
+            JsonPayloadError::ContentType => ApiError::InvalidRequest("Content-Type must be application/json".to_string()),
+            error => ApiError::InvalidRequest(error.to_string()),
+        }
+    }
+}
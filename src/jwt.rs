@@ -0,0 +1,190 @@
+use crate::{error::ApiError, config::{ConfigErrors, non_empty}, auth::{Caller, Scope, hash_api_key}};
+use std::{collections::HashMap, fs, str::FromStr, sync::Mutex, time::{Duration, Instant}};
+use actix_web::web::block;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+use log::error;
+
+/// Shorter HS256 secrets are rejected, as they can be guessed offline from any token.
+const MIN_HS256_SECRET_LEN: usize = 32;
+
+/// Shortest time between two JWKS fetches triggered by tokens with an unknown `kid`.
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct JwtConfig {
+    pub hs256_secret: Option<String>,
+    /// PEM contents of `JWT_PUBLIC_KEY_FILE`, read at startup.
+    pub public_key: Option<String>,
+    pub jwks: Option<String>,
+    pub jwks_cache_secs: u64,
+    pub audience: Option<String>,
+    pub issuer: Option<String>,
+    pub scope_claim: String,
+}
+
+/// Reads the `JWT_*` variables; bearer tokens are only accepted when a key source is configured.
+pub fn init_jwt(errors: &mut ConfigErrors) -> Option<JwtConfig> {
+    let hs256_secret = non_empty("JWT_HS256_SECRET");
+
+    if hs256_secret.as_ref().map_or(false, |secret| secret.len() < MIN_HS256_SECRET_LEN) {
+        errors.push(format!("JWT_HS256_SECRET must be at least {} bytes", MIN_HS256_SECRET_LEN));
+    }
+
+    let config = JwtConfig {
+        hs256_secret,
+        public_key: non_empty("JWT_PUBLIC_KEY_FILE").and_then(|file| match fs::read_to_string(&file) {
+            Ok(pem) => Some(pem),
+            Err(error) => {
+                errors.push(format!("JWT_PUBLIC_KEY_FILE {}: {}", file, error));
+                None
+            }
+        }),
+        jwks: non_empty("JWT_JWKS"),
+        jwks_cache_secs: errors.parse::<u64>("JWT_JWKS_CACHE_SECS", 300),
+        audience: non_empty("JWT_AUDIENCE"),
+        issuer: non_empty("JWT_ISSUER"),
+        scope_claim: non_empty("JWT_SCOPE_CLAIM").unwrap_or_else(|| "scope".to_string()),
+    };
+
+    match config.hs256_secret.is_some() || config.public_key.is_some() || config.jwks.is_some() {
+        true => Some(config),
+        false => None
+    }
+}
+
+pub struct JwtVerifier {
+    config: JwtConfig,
+    jwks: Mutex<Option<(Instant, JwkSet)>>,
+    refreshed: Mutex<Option<Instant>>,
+}
+
+impl JwtVerifier {
+    pub fn new(config: JwtConfig) -> Self {
+        JwtVerifier {
+            config,
+            jwks: Mutex::new(None),
+            refreshed: Mutex::new(None),
+        }
+    }
+
+    async fn fetch_jwks(&self, source: &str) -> Result<JwkSet, String> {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            let path = source.to_string();
+            let contents = block(move || fs::read_to_string(path)).await.map_err(|error| error.to_string())?.map_err(|error| error.to_string())?;
+            return serde_json::from_str(&contents).map_err(|error| error.to_string());
+        }
+
+        let awc_client = awc::Client::new();
+
+        let mut response = awc_client.get(source).send().await.map_err(|error| error.to_string())?;
+
+        response.json::<JwkSet>().limit(1024 * 1024).await.map_err(|error| error.to_string())
+    }
+
+    /// Whether a refetch for an unknown `kid` may run now, at most once per `JWKS_REFRESH_INTERVAL`.
+    fn may_refresh(&self) -> bool {
+        let mut refreshed = self.refreshed.lock().unwrap();
+
+        match *refreshed {
+            Some(last) if last.elapsed() < JWKS_REFRESH_INTERVAL => false,
+            _ => {
+                *refreshed = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    async fn jwks(&self, source: &str, refresh: bool) -> Result<JwkSet, ApiError> {
+        let ttl = Duration::from_secs(self.config.jwks_cache_secs);
+
+        if !refresh || !self.may_refresh() {
+            if let Some((fetched, jwks)) = self.jwks.lock().unwrap().as_ref() {
+                if refresh || fetched.elapsed() < ttl {
+                    return Ok(jwks.clone());
+                }
+            }
+        }
+
+        match self.fetch_jwks(source).await {
+            Ok(jwks) => {
+                *self.jwks.lock().unwrap() = Some((Instant::now(), jwks.clone()));
+                Ok(jwks)
+            },
+            Err(reason) => {
+                error!("Loading JWKS from {} failed: {}", source, reason);
+                Err(ApiError::InvalidToken("signing keys unavailable".to_string()))
+            }
+        }
+    }
+
+    async fn decoding_key(&self, alg: Algorithm, kid: Option<String>) -> Result<DecodingKey, ApiError> {
+        let invalid_key = |error: jsonwebtoken::errors::Error| ApiError::InvalidToken(error.to_string());
+
+        match alg {
+            Algorithm::HS256 => match &self.config.hs256_secret {
+                Some(secret) => Ok(DecodingKey::from_secret(secret.as_bytes())),
+                None => Err(ApiError::InvalidToken("HS256 tokens are not accepted".to_string()))
+            },
+            Algorithm::RS256 | Algorithm::ES256 => {
+                if let Some(source) = &self.config.jwks {
+                    let kid = kid.ok_or_else(|| ApiError::InvalidToken("token header has no kid".to_string()))?;
+                    let mut jwks = self.jwks(source, false).await?;
+                    if jwks.find(&kid).is_none() {
+                        jwks = self.jwks(source, true).await?;
+                    }
+                    return match jwks.find(&kid) {
+                        Some(jwk) => DecodingKey::from_jwk(jwk).map_err(invalid_key),
+                        None => Err(ApiError::InvalidToken(format!("unknown kid \"{}\"", kid)))
+                    };
+                }
+
+                match (&self.config.public_key, alg) {
+                    (Some(pem), Algorithm::RS256) => DecodingKey::from_rsa_pem(pem.as_bytes()).map_err(invalid_key),
+                    (Some(pem), Algorithm::ES256) => DecodingKey::from_ec_pem(pem.as_bytes()).map_err(invalid_key),
+                    _ => Err(ApiError::InvalidToken(format!("{:?} tokens are not accepted", alg)))
+                }
+            },
+            alg => Err(ApiError::InvalidToken(format!("{:?} tokens are not accepted", alg)))
+        }
+    }
+
+    /// Validates a bearer token and maps its scope claim onto route scopes.
+    pub async fn verify(&self, token: &str) -> Result<Caller, ApiError> {
+        let header = decode_header(token).map_err(|error| ApiError::InvalidToken(error.to_string()))?;
+
+        let key = self.decoding_key(header.alg, header.kid).await?;
+
+        let mut validation = Validation::new(header.alg);
+        if let Some(audience) = &self.config.audience {
+            validation.set_audience(&[audience]);
+        }
+        if let Some(issuer) = &self.config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+
+        let claims = decode::<HashMap<String, Value>>(token, &key, &validation)
+            .map_err(|error| ApiError::InvalidToken(error.to_string()))?
+            .claims;
+
+        let scopes: Vec<Scope> = match claims.get(&self.config.scope_claim) {
+            Some(Value::String(scopes)) => scopes.split_whitespace().filter_map(|scope| Scope::from_str(scope).ok()).collect(),
+            Some(Value::Array(scopes)) => scopes.iter().filter_map(|scope| scope.as_str()).filter_map(|scope| Scope::from_str(scope).ok()).collect(),
+            _ => [].to_vec()
+        };
+
+        let signers: Vec<String> = match claims.get("signers") {
+            Some(Value::String(signers)) => signers.split_whitespace().map(|signer| signer.to_lowercase()).collect(),
+            Some(Value::Array(signers)) => signers.iter().filter_map(|signer| signer.as_str()).map(|signer| signer.to_lowercase()).collect(),
+            _ => [].to_vec()
+        };
+
+        let (name, id) = match (claims.get("sub"), claims.get("iss")) {
+            (Some(Value::String(sub)), Some(Value::String(iss))) => (sub.to_string(), format!("jwt:{}:{}", iss, sub)),
+            (Some(Value::String(sub)), _) => (sub.to_string(), format!("jwt::{}", sub)),
+            _ => ("jwt".to_string(), format!("token:{}", hash_api_key(token)))
+        };
+
+        Ok(Caller { name, id, scopes, signers })
+    }
+}
@@ -0,0 +1,40 @@
+//! SugarFunge integration API.
+//!
+//! The server binary is built from these modules; other services can depend on the crate for the
+//! request and response types and call a running server through [`client::Client`].
+
+pub mod config;
+pub mod auth;
+pub mod jwt;
+pub mod ratelimit;
+pub mod signer;
+pub mod eip712;
+#[cfg(feature = "relayer")]
+pub mod relayer;
+pub mod voucher;
+pub mod balance;
+pub mod error;
+pub mod extract;
+pub mod batch;
+pub mod airdrop;
+pub mod artifacts;
+pub mod contracts;
+pub mod chain;
+pub mod asset;
+#[cfg(feature = "moralis")]
+pub mod moralis;
+#[cfg(feature = "wrapper")]
+pub mod wrapper;
+pub mod events;
+pub mod webhook;
+pub mod stream;
+pub mod openapi;
+pub mod client;
+
+pub use asset::{AssetData, AssetMint, AssetTransfer, AssetBatchTransfer, TxResponse};
+#[cfg(feature = "wrapper")]
+pub use wrapper::{Wrap1155, BatchWrap1155, GetWrapped1155, Unwrap1155};
+#[cfg(feature = "moralis")]
+pub use moralis::QueryParams;
+pub use error::ErrorResponse;
+pub use client::{Client, ClientError};
@@ -1,17 +1,18 @@
-mod config;
-mod error;
-mod asset;
-mod moralis;
-mod wrapper;
-
 use actix_cors::Cors;
-use asset::*;
-use moralis::*;
-use wrapper::*;
-use actix_web::{HttpServer, App, web::Data, http};
+use sugarfunge_integration::{config::{self, Config}, extract, openapi, balance, events, auth::Authentication, jwt::JwtVerifier, ratelimit::{RateLimit, init_limiter}};
+use sugarfunge_integration::{asset::*, webhook::*, stream::*, voucher::*, batch::*, airdrop::*};
+use std::sync::Arc;
+use actix_web::{HttpServer, App, web::{self, Data, ServiceConfig}, http};
+#[cfg(feature = "moralis")]
+use sugarfunge_integration::moralis;
+#[cfg(feature = "wrapper")]
+use sugarfunge_integration::wrapper;
+#[cfg(feature = "relayer")]
+use sugarfunge_integration::relayer;
 use actix_web_prom::PrometheusMetricsBuilder;
 use serde::{Serialize, Deserialize};
 use dotenv::dotenv;
+use clap::Parser;
 
 #[derive(Serialize, Deserialize, Debug)]
 enum ContentType {
@@ -26,51 +27,179 @@ impl ContentType {
     }
 }
 
+/// Moralis query routes, registered when the `moralis` feature is built and `MORALIS_API_KEY` is set.
+#[cfg(feature = "moralis")]
+fn queries(cfg: &mut ServiceConfig, config: &Config, v1: bool) {
+    match (&config.moralis, v1) {
+        (Some(_), false) => moralis::configure(cfg),
+        (Some(_), true) => moralis::configure_v1(cfg),
+        (None, _) => (),
+    }
+}
+
+#[cfg(not(feature = "moralis"))]
+fn queries(_cfg: &mut ServiceConfig, _config: &Config, _v1: bool) {}
+
+/// ERC1155 wrapper routes, registered when the `wrapper` feature is built.
+#[cfg(feature = "wrapper")]
+fn wraps(cfg: &mut ServiceConfig, v1: bool) {
+    match v1 {
+        false => wrapper::configure(cfg),
+        true => wrapper::configure_v1(cfg),
+    }
+}
+
+#[cfg(not(feature = "wrapper"))]
+fn wraps(_cfg: &mut ServiceConfig, _v1: bool) {}
+
+/// Meta-transaction routes, registered when the `relayer` feature is built.
+#[cfg(feature = "relayer")]
+fn relays(cfg: &mut ServiceConfig, v1: bool) {
+    match v1 {
+        false => relayer::configure(cfg),
+        true => relayer::configure_v1(cfg),
+    }
+}
+
+#[cfg(not(feature = "relayer"))]
+fn relays(_cfg: &mut ServiceConfig, _v1: bool) {}
+
+/// SugarFunge integration API server.
+#[derive(Parser)]
+#[command(name = "sugarfunge-integration", version)]
+struct Args {
+    /// TOML or YAML configuration file (Default: CONFIG_FILE)
+    #[arg(long)]
+    config: Option<String>,
+    /// Validate the configuration and exit without starting the server
+    #[arg(long)]
+    check_config: bool,
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
 
     dotenv().ok();
     env_logger::init();
 
+    let args = Args::parse();
+
+    if args.check_config {
+        match config::load(args.config) {
+            Ok(_) => println!("Configuration is valid"),
+            Err(errors) => {
+                config::report(&errors);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     let prometheus = PrometheusMetricsBuilder::new("api")
         .endpoint("/metrics")
         .build()
         .unwrap();
 
-    let env = config::init();
+    let env = config::init(args.config);
 
     let url = env.listen_url.to_owned();
 
+    if env.auth_disabled {
+        log::warn!("AUTH_DISABLED is set, every route is reachable without credentials");
+    }
+
+    #[cfg(feature = "moralis")]
+    if env.moralis.is_none() {
+        log::warn!("MORALIS_API_KEY is not set, the NFT query routes are disabled");
+    }
+
+    let jwt = env.jwt.clone().map(|jwt| Arc::new(JwtVerifier::new(jwt)));
+
+    let limiter = init_limiter(&env, &prometheus.registry);
+
+    actix_web::rt::spawn(balance::watch(env.clone(), balance::balance_gauge(&prometheus.registry)));
+
+    let webhooks = Data::new(WebhookStore::default());
+    let hub = Data::new(EventHub::default());
+    let (nonces, airdrops) = match (VoucherNonces::load(&env), AirdropJobs::load(&env)) {
+        (Ok(nonces), Ok(airdrops)) => (Data::new(nonces), Data::new(airdrops)),
+        (nonces, airdrops) => {
+            config::report(&[nonces.err(), airdrops.err()].into_iter().flatten().collect::<Vec<String>>());
+            std::process::exit(1);
+        }
+    };
+    let jobs = Data::new(BatchJobs::default());
+    let config = Data::new(env.clone());
+
+    for id in airdrops.running() {
+        actix_web::rt::spawn(run_airdrop(config.clone(), airdrops.clone(), hub.clone(), id));
+    }
+
+    for chain in env.chains.all() {
+        actix_web::rt::spawn(events::watch(env.clone(), chain.clone(), webhooks.clone(), hub.clone()));
+    }
+
     HttpServer::new( move || {
         let cors = Cors::default()
             .allowed_origin_fn(|origin, _req_head| {
                 origin.as_bytes().starts_with(b"http://localhost")
             })
-            .allowed_methods(vec!["GET", "POST"])
-            .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
+            .allowed_methods(vec!["GET", "POST", "DELETE"])
+            .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT, http::header::HeaderName::from_static("x-api-key")])
             .allowed_header(http::header::CONTENT_TYPE)
             .max_age(3600);
         App::new()
+            .wrap(RateLimit::new(limiter.clone()).write("redeem_mint_voucher").write("redeem_voucher"))
+            .wrap(Authentication::new(&env, jwt.clone()))
             .wrap(prometheus.clone())
             .wrap(cors)
+            .configure(openapi::configure)
             .service(mint_nft)
             .service(transfer_nft)
             .service(batch_transfer_nft)
-            .service(get_nfts)
-            .service(get_contract_nfts)
-            .service(get_nft_transfers)
-            .service(get_nft_transfers_by_block)
-            .service(get_all_token_ids)
-            .service(get_contract_nft_transfers)
-            .service(get_nft_metadata)
-            .service(get_nft_owners)
-            .service(get_token_id_metadata)
-            .service(get_token_id_owners)
-            .service(wrap_1155)
-            .service(batch_wrap_1155)
-            .service(unwrap_1155)
-            .service(get_wrapped_1155)
-            .app_data(Data::new(env.clone()))
+            .configure(|cfg| queries(cfg, &env, false))
+            .configure(|cfg| wraps(cfg, false))
+            .service(subscribe_webhook)
+            .service(unsubscribe_webhook)
+            .service(get_webhooks)
+            .service(get_webhook_dead_letters)
+            .service(retry_webhook_dead_letters)
+            .service(event_stream)
+            .configure(|cfg| relays(cfg, false))
+            .service(create_mint_voucher)
+            .service(redeem_mint_voucher)
+            .service(get_batch_job)
+            .service(create_mint_airdrop)
+            .service(create_transfer_airdrop)
+            .service(get_airdrop)
+            .service(get_airdrops)
+            .service(web::scope("/v1")
+                .configure(|cfg| queries(cfg, &env, true))
+                .service(mint_token)
+                .service(transfer_token)
+                .service(batch_transfer_tokens)
+                .configure(|cfg| wraps(cfg, true))
+                .service(list_webhooks)
+                .service(create_webhook)
+                .service(list_dead_letters)
+                .service(retry_dead_letters)
+                .service(delete_webhook)
+                .service(event_stream)
+                .configure(|cfg| relays(cfg, true))
+                .service(create_voucher)
+                .service(redeem_voucher)
+                .service(get_job)
+                .service(start_mint_airdrop)
+                .service(start_transfer_airdrop)
+                .service(list_airdrops)
+                .service(get_airdrop_job))
+            .app_data(config.clone())
+            .app_data(extract::json_config(&env))
+            .app_data(webhooks.clone())
+            .app_data(hub.clone())
+            .app_data(nonces.clone())
+            .app_data(jobs.clone())
+            .app_data(airdrops.clone())
     })
     .bind(url)?
     .run()
@@ -1,57 +1,82 @@
-use crate::{error::ApiError, config::Config};
-use actix_web::{http::StatusCode, post, web::{Data, Json}, Responder};
+use crate::{error::ApiError, config::{Config, non_empty}, extract::ValidJson};
+use actix_web::{http::StatusCode, get, post, web::{Data, Json, Path, Query, ServiceConfig}, Responder};
 use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
 use serde_json::Value;
 use log::error;
 use snailquote::unescape;
+use validator::Validate;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Address {
-    address: String,
-    options: QueryParams
+#[derive(Clone, Debug)]
+pub struct MoralisConfig {
+    pub base_url: String,
+    pub api_key: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Token {
-    token_address: String,
-    options: QueryParams
+/// Moralis is enabled by its `[moralis]` section: without `MORALIS_API_KEY` the query routes are left out.
+pub fn init_moralis() -> Option<MoralisConfig> {
+    non_empty("MORALIS_API_KEY").map(|api_key| MoralisConfig {
+        base_url: non_empty("MORALIS_BASE_URL").unwrap_or_else(|| "https://deep-index.moralis.io/api/v2/".to_string()),
+        api_key,
+    })
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct AccountToken {
-    address: String,
-    token_address: String,
-    options: QueryParams
+pub fn moralis_config(config: &Config) -> Result<&MoralisConfig, ApiError> {
+    config.moralis.as_ref().ok_or_else(|| ApiError::NotConfigured("Moralis".to_string()))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TokenId {
-    token_address: String,
-    id: u64,
-    options: QueryParams
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct Address {
+    #[validate(length(min = 1))]
+    pub address: String,
+    pub options: QueryParams
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct BlockNumber {
-    block: u64,
-    options: QueryParams
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct Token {
+    #[validate(length(min = 1))]
+    pub token_address: String,
+    pub options: QueryParams
 }
 
-#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct AccountToken {
+    #[validate(length(min = 1))]
+    pub address: String,
+    #[validate(length(min = 1))]
+    pub token_address: String,
+    pub options: QueryParams
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct TokenId {
+    #[validate(length(min = 1))]
+    pub token_address: String,
+    pub id: u64,
+    pub options: QueryParams
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct BlockNumber {
+    pub block: u64,
+    pub options: QueryParams
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct QueryParams {
-    chain: Option<String>,
-    format: Option<String>,
-    offset: Option<u64>,
-    limit: Option<u64>
+    pub chain: Option<String>,
+    pub format: Option<String>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>
 }
 
-pub fn check_query_params(params: &QueryParams) -> QueryParams {
+/// Fills in the defaults. `chain` is looked up in the chain registry, by name, chain id or Moralis slug,
+/// so reads go to the same network as writes; without it the default chain is queried.
+pub fn check_query_params(config: &Config, params: &QueryParams) -> Result<QueryParams, ApiError> {
 
-    QueryParams {
-        chain: match &params.chain {
-            Some(chain) => Some(chain.to_string()),
-            None => Some("ropsten".to_string()),
-        },
+    Ok(QueryParams {
+        chain: Some(config.chains.select(&params.chain)?.moralis_chain.to_owned()),
         format: match &params.format {
             Some(format) => Some(format.to_string()),
             None => Some("decimal".to_string()),
@@ -64,16 +89,61 @@ pub fn check_query_params(params: &QueryParams) -> QueryParams {
             Some(limit) => Some(*limit),
             None => Some(10),
         },
+    })
+}
+
+/// A Moralis NFT query, addressed the same way as the `/v1` read routes.
+#[derive(Debug, Clone)]
+pub enum NftQuery {
+    AccountNfts(String),
+    AccountContractNfts(String, String),
+    AccountTransfers(String),
+    BlockTransfers(u64),
+    ContractTokens(String),
+    ContractTransfers(String),
+    ContractMetadata(String),
+    ContractOwners(String),
+    TokenMetadata(String, u64),
+    TokenOwners(String, u64),
+}
+
+impl NftQuery {
+    pub fn url(&self, moralis: &MoralisConfig) -> String {
+        let base = &moralis.base_url;
+
+        match self {
+            Self::AccountNfts(address) => format!("{}{}/nft", base, address),
+            Self::AccountContractNfts(address, token_address) => format!("{}{}/nft/{}", base, address, token_address),
+            Self::AccountTransfers(address) => format!("{}{}/nft/transfers", base, address),
+            Self::BlockTransfers(block) => format!("{}block/{}/nft/transfers", base, block),
+            Self::ContractTokens(token_address) => format!("{}nft/{}", base, token_address),
+            Self::ContractTransfers(token_address) => format!("{}nft/{}/transfers", base, token_address),
+            Self::ContractMetadata(token_address) => format!("{}nft/{}/metadata", base, token_address),
+            Self::ContractOwners(token_address) => format!("{}nft/{}/owners", base, token_address),
+            Self::TokenMetadata(token_address, id) => format!("{}nft/{}/{}", base, token_address, id),
+            Self::TokenOwners(token_address, id) => format!("{}nft/{}/{}/owners", base, token_address, id),
+        }
     }
 }
 
+/// Runs `query` with the defaults of `check_query_params` filled in.
+pub async fn moralis_query(config: &Config, query: &NftQuery, params: &QueryParams) -> Result<Value, ApiError> {
+    moralis_get(config, &query.url(moralis_config(config)?), check_query_params(config, params)?).await
+}
+
 pub async fn moralis_call(config: &Config, url: &String, params: QueryParams) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_get(config, url, params).await?))
+}
+
+pub async fn moralis_get(config: &Config, url: &String, params: QueryParams) -> Result<Value, ApiError> {
+
+    let api_key = moralis_config(config)?.api_key.to_owned();
 
     let awc_client = awc::Client::new();
 
     let response = 
         awc_client.get(url)
-            .insert_header(("X-API-Key", config.moralis_api_key.to_owned()))
+            .insert_header(("X-API-Key", api_key))
             .query(&params).unwrap()
             .send()
             .await;
@@ -81,7 +151,7 @@ pub async fn moralis_call(config: &Config, url: &String, params: QueryParams) ->
     match response {
         Ok(mut response) => {
             let body_str: String = std::str::from_utf8(&response.body().await.unwrap()).unwrap().to_string();
-            let body: Json<Value> = Json(serde_json::from_str(&body_str).unwrap());
+            let body: Value = serde_json::from_str(&body_str).unwrap();
             
             match response.status() {
                 StatusCode::OK => Ok(body),
@@ -95,92 +165,389 @@ pub async fn moralis_call(config: &Config, url: &String, params: QueryParams) ->
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_nfts",
+    tag = "legacy",
+    request_body = Address,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_nfts")]
-async fn get_nfts(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Address = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + &unescape(&req_data.address).unwrap() + "/nft";
+async fn get_nfts(req_data: ValidJson<Address>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + &unescape(&req_data.address).unwrap() + "/nft";
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_contract_nfts",
+    tag = "legacy",
+    request_body = AccountToken,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_contract_nfts")]
-async fn get_contract_nfts(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: AccountToken = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + &unescape(&req_data.address).unwrap() + "/nft/" + &unescape(&req_data.token_address).unwrap();
+async fn get_contract_nfts(req_data: ValidJson<AccountToken>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + &unescape(&req_data.address).unwrap() + "/nft/" + &unescape(&req_data.token_address).unwrap();
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_nft_transfers",
+    tag = "legacy",
+    request_body = Address,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_nft_transfers")]
-async fn get_nft_transfers(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Address = serde_json::from_str(&req_body)?;
+async fn get_nft_transfers(req_data: ValidJson<Address>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.address).unwrap() + "/transfers";
 
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.address).unwrap() + "/transfers";
-
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_nft_transfers_by_block",
+    tag = "legacy",
+    request_body = BlockNumber,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_nft_transfers_by_block")]
-async fn get_nft_transfers_by_block(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: BlockNumber = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + "block/" + &req_data.block.to_string() + "/nft/transfers";
+async fn get_nft_transfers_by_block(req_data: ValidJson<BlockNumber>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "block/" + &req_data.block.to_string() + "/nft/transfers";
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_all_token_ids",
+    tag = "legacy",
+    request_body = Token,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_all_token_ids")]
-async fn get_all_token_ids(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Token = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap();
+async fn get_all_token_ids(req_data: ValidJson<Token>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap();
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_contract_nft_transfers",
+    tag = "legacy",
+    request_body = Token,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_contract_nft_transfers")]
-async fn get_contract_nft_transfers(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Token = serde_json::from_str(&req_body)?;
+async fn get_contract_nft_transfers(req_data: ValidJson<Token>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/transfers";
 
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/transfers";
-
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_nft_metadata",
+    tag = "legacy",
+    request_body = Token,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_nft_metadata")]
-async fn get_nft_metadata(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Token = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/metadata";
+async fn get_nft_metadata(req_data: ValidJson<Token>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/metadata";
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_nft_owners",
+    tag = "legacy",
+    request_body = Token,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_nft_owners")]
-async fn get_nft_owners(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Token = serde_json::from_str(&req_body)?;
-
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/owners";
+async fn get_nft_owners(req_data: ValidJson<Token>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/owners";
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_token_id_metadata",
+    tag = "legacy",
+    request_body = TokenId,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_token_id_metadata")]
-async fn get_token_id_metadata(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: TokenId = serde_json::from_str(&req_body)?;
+async fn get_token_id_metadata(req_data: ValidJson<TokenId>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/" + &req_data.id.to_string() ;
 
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/" + &req_data.id.to_string() ;
-
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
 }
 
+#[utoipa::path(
+    post,
+    path = "/get_token_id_owners",
+    tag = "legacy",
+    request_body = TokenId,
+    responses(
+        (status = 200, description = "Moralis response"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_token_id_owners")]
-async fn get_token_id_owners(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: TokenId = serde_json::from_str(&req_body)?;
+async fn get_token_id_owners(req_data: ValidJson<TokenId>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let url: String = moralis_config(&config)?.base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/" + &req_data.id.to_string() + "/owners";
+
+    moralis_call(&config, &url, check_query_params(&config, &req_data.options)?).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/accounts/{address}/nfts",
+    tag = "queries",
+    params(
+        ("address" = String, Path, description = "Account address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "NFTs owned by the account"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/accounts/{address}/nfts")]
+async fn list_account_nfts(address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::AccountNfts(address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/accounts/{address}/contracts/{token_address}/nfts",
+    tag = "queries",
+    params(
+        ("address" = String, Path, description = "Account address"),
+        ("token_address" = String, Path, description = "Contract address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "NFTs of one contract owned by the account"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/accounts/{address}/contracts/{token_address}/nfts")]
+async fn list_account_contract_nfts(path: Path<(String, String)>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let (address, token_address) = path.into_inner();
+
+    Ok(Json(moralis_query(&config, &NftQuery::AccountContractNfts(address, token_address), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/accounts/{address}/transfers",
+    tag = "queries",
+    params(
+        ("address" = String, Path, description = "Account address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "NFT transfers to or from the account"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/accounts/{address}/transfers")]
+async fn list_account_transfers(address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::AccountTransfers(address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/blocks/{block}/transfers",
+    tag = "queries",
+    params(
+        ("block" = u64, Path, description = "Block number"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "NFT transfers in the block"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/blocks/{block}/transfers")]
+async fn list_block_transfers(block: Path<u64>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::BlockTransfers(block.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/tokens",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Token ids of the contract"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/tokens")]
+async fn list_contract_tokens(token_address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::ContractTokens(token_address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/transfers",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Transfers of the contract"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/transfers")]
+async fn list_contract_transfers(token_address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::ContractTransfers(token_address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/metadata",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Contract metadata"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/metadata")]
+async fn get_contract_metadata(token_address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::ContractMetadata(token_address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/owners",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Owners of the contract's tokens"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/owners")]
+async fn list_contract_owners(token_address: Path<String>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(moralis_query(&config, &NftQuery::ContractOwners(token_address.into_inner()), &options).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/tokens/{id}",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        ("id" = u64, Path, description = "Token id"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Token metadata"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/tokens/{id}")]
+async fn get_token_metadata(path: Path<(String, u64)>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let (token_address, id) = path.into_inner();
+
+    Ok(Json(moralis_query(&config, &NftQuery::TokenMetadata(token_address, id), &options).await?))
+}
 
-    let url: String = config.moralis_base_url.to_owned() + "nft/" + &unescape(&req_data.token_address).unwrap() + "/" + &req_data.id.to_string() + "/owners";
+#[utoipa::path(
+    get,
+    path = "/v1/contracts/{token_address}/tokens/{id}/owners",
+    tag = "queries",
+    params(
+        ("token_address" = String, Path, description = "Contract address"),
+        ("id" = u64, Path, description = "Token id"),
+        QueryParams
+    ),
+    responses(
+        (status = 200, description = "Owners of the token"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/contracts/{token_address}/tokens/{id}/owners")]
+async fn list_token_owners(path: Path<(String, u64)>, options: Query<QueryParams>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let (token_address, id) = path.into_inner();
+
+    Ok(Json(moralis_query(&config, &NftQuery::TokenOwners(token_address, id), &options).await?))
+}
+
+/// Registers the legacy query routes.
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_nfts)
+        .service(get_contract_nfts)
+        .service(get_nft_transfers)
+        .service(get_nft_transfers_by_block)
+        .service(get_all_token_ids)
+        .service(get_contract_nft_transfers)
+        .service(get_nft_metadata)
+        .service(get_nft_owners)
+        .service(get_token_id_metadata)
+        .service(get_token_id_owners);
+}
 
-    moralis_call(&config, &url, check_query_params(&req_data.options)).await
+/// Registers the `/v1` query routes, inside the `/v1` scope.
+pub fn configure_v1(cfg: &mut ServiceConfig) {
+    cfg.service(list_account_nfts)
+        .service(list_account_contract_nfts)
+        .service(list_account_transfers)
+        .service(list_block_transfers)
+        .service(list_contract_tokens)
+        .service(list_contract_transfers)
+        .service(get_contract_metadata)
+        .service(list_contract_owners)
+        .service(get_token_metadata)
+        .service(list_token_owners);
 }
@@ -0,0 +1,229 @@
+use crate::{error, asset, webhook, events, voucher, batch, airdrop, stream, auth::API_KEY_HEADER};
+use actix_web::web::ServiceConfig;
+#[cfg(not(feature = "swagger-ui"))]
+use actix_web::{get, web::Json, Responder};
+use utoipa::{Modify, OpenApi, openapi::{self, security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme}}};
+#[cfg(feature = "swagger-ui")]
+use utoipa_swagger_ui::SwaggerUi;
+#[cfg(feature = "moralis")]
+use crate::moralis;
+#[cfg(feature = "wrapper")]
+use crate::wrapper;
+#[cfg(feature = "relayer")]
+use crate::relayer;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        asset::mint_nft,
+        asset::transfer_nft,
+        asset::batch_transfer_nft,
+        webhook::subscribe_webhook,
+        webhook::unsubscribe_webhook,
+        webhook::get_webhooks,
+        webhook::get_webhook_dead_letters,
+        webhook::retry_webhook_dead_letters,
+        voucher::create_mint_voucher,
+        voucher::redeem_mint_voucher,
+        batch::get_batch_job,
+        airdrop::create_mint_airdrop,
+        airdrop::create_transfer_airdrop,
+        airdrop::get_airdrop,
+        airdrop::get_airdrops,
+        asset::mint_token,
+        asset::transfer_token,
+        asset::batch_transfer_tokens,
+        webhook::list_webhooks,
+        webhook::create_webhook,
+        webhook::delete_webhook,
+        webhook::list_dead_letters,
+        webhook::retry_dead_letters,
+        stream::event_stream,
+        voucher::create_voucher,
+        voucher::redeem_voucher,
+        batch::get_job,
+        airdrop::start_mint_airdrop,
+        airdrop::start_transfer_airdrop,
+        airdrop::list_airdrops,
+        airdrop::get_airdrop_job
+    ),
+    components(schemas(
+        error::ErrorResponse,
+        asset::AssetData,
+        asset::AssetMint,
+        asset::AssetTransfer,
+        asset::AssetBatchTransfer,
+        asset::TxResponse,
+        webhook::Webhook,
+        webhook::SubscribeWebhook,
+        webhook::WebhookId,
+        webhook::DeadLetter,
+        events::EventKind,
+        events::EventFilter,
+        events::ContractEvent,
+        voucher::CreateMintVoucher,
+        voucher::MintVoucher,
+        voucher::RedeemMintVoucher,
+        batch::BatchIssue,
+        batch::JobState,
+        batch::ChunkState,
+        batch::BatchChunk,
+        batch::BatchJob,
+        batch::BatchJobId,
+        airdrop::AirdropMode,
+        airdrop::RowState,
+        airdrop::AirdropEntry,
+        airdrop::AirdropRow,
+        airdrop::AirdropJob,
+        airdrop::AirdropSummary,
+        airdrop::AirdropId
+    )),
+    tags(
+        (name = "tokens", description = "Mint and transfer SugarFunge assets"),
+        (name = "webhooks", description = "Contract event subscriptions"),
+        (name = "events", description = "Server-sent contract events and transaction status"),
+        (name = "vouchers", description = "Signed mint vouchers redeemed by their recipient"),
+        (name = "jobs", description = "Batches split into several transactions"),
+        (name = "airdrops", description = "Bulk mints and transfers from an uploaded list"),
+        (name = "legacy", description = "The original POST routes, kept next to their /v1 replacements")
+    ),
+    modifiers(&SecuritySchemes),
+    security(
+        ("api_key" = []),
+        ("bearer" = [])
+    )
+)]
+pub struct ApiDoc;
+
+/// The Moralis query routes, merged into `ApiDoc` when the `moralis` feature is built.
+#[cfg(feature = "moralis")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        moralis::get_nfts,
+        moralis::get_contract_nfts,
+        moralis::get_nft_transfers,
+        moralis::get_nft_transfers_by_block,
+        moralis::get_all_token_ids,
+        moralis::get_contract_nft_transfers,
+        moralis::get_nft_metadata,
+        moralis::get_nft_owners,
+        moralis::get_token_id_metadata,
+        moralis::get_token_id_owners,
+        moralis::list_account_nfts,
+        moralis::list_account_contract_nfts,
+        moralis::list_account_transfers,
+        moralis::list_block_transfers,
+        moralis::list_contract_tokens,
+        moralis::list_contract_transfers,
+        moralis::get_contract_metadata,
+        moralis::list_contract_owners,
+        moralis::get_token_metadata,
+        moralis::list_token_owners
+    ),
+    components(schemas(
+        moralis::Address,
+        moralis::Token,
+        moralis::AccountToken,
+        moralis::TokenId,
+        moralis::BlockNumber,
+        moralis::QueryParams
+    )),
+    tags(
+        (name = "queries", description = "NFT data served through Moralis")
+    )
+)]
+struct QueryDoc;
+
+/// The ERC1155 wrapper routes, merged into `ApiDoc` when the `wrapper` feature is built.
+#[cfg(feature = "wrapper")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        wrapper::wrap_1155,
+        wrapper::batch_wrap_1155,
+        wrapper::unwrap_1155,
+        wrapper::get_wrapped_1155,
+        wrapper::wrap_token,
+        wrapper::batch_wrap_tokens,
+        wrapper::unwrap_token,
+        wrapper::get_wrapped_token
+    ),
+    components(schemas(
+        wrapper::Wrap1155,
+        wrapper::BatchWrap1155,
+        wrapper::GetWrapped1155,
+        wrapper::Unwrap1155
+    )),
+    tags(
+        (name = "wraps", description = "Wrap ERC1155 tokens into SugarFunge assets and back")
+    )
+)]
+struct WrapDoc;
+
+/// The meta-transaction routes, merged into `ApiDoc` when the `relayer` feature is built.
+#[cfg(feature = "relayer")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        relayer::build_meta_transfer,
+        relayer::verify_meta_transfer,
+        relayer::relay_meta_transfer,
+        relayer::build_forward_request,
+        relayer::verify_forward_request,
+        relayer::relay_forward_request
+    ),
+    components(schemas(
+        relayer::MetaTransfer,
+        relayer::ForwardRequestMessage,
+        relayer::SignedForwardRequest
+    )),
+    tags(
+        (name = "meta-transfers", description = "Gasless transfers relayed through the EIP-2771 forwarder")
+    )
+)]
+struct RelayDoc;
+
+/// Describes the `X-API-Key` header and the JWT bearer token checked by `Authentication`.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(openapi::Components::new);
+
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))));
+        components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()));
+    }
+}
+
+/// `ApiDoc` with the routes of every enabled feature.
+pub fn document() -> openapi::OpenApi {
+    #[allow(unused_mut)]
+    let mut document = ApiDoc::openapi();
+
+    #[cfg(feature = "moralis")]
+    document.merge(QueryDoc::openapi());
+
+    #[cfg(feature = "wrapper")]
+    document.merge(WrapDoc::openapi());
+
+    #[cfg(feature = "relayer")]
+    document.merge(RelayDoc::openapi());
+
+    document
+}
+
+#[cfg(not(feature = "swagger-ui"))]
+#[get("/openapi.json")]
+async fn openapi_json() -> impl Responder {
+    Json(document())
+}
+
+/// Serves the document at `/openapi.json` and, with the `swagger-ui` feature, the UI at `/swagger-ui/`.
+pub fn configure(cfg: &mut ServiceConfig) {
+    #[cfg(feature = "swagger-ui")]
+    cfg.service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", document()));
+
+    #[cfg(not(feature = "swagger-ui"))]
+    cfg.service(openapi_json);
+}
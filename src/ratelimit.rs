@@ -0,0 +1,288 @@
+use crate::{error::ApiError, config::{Config, ConfigErrors}, auth::{Caller, Scope, required_scope}};
+use std::{collections::HashMap, rc::Rc, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use actix_web::{body::EitherBody, dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform}, Error, HttpMessage};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use prometheus::{IntCounterVec, Opts, Registry};
+
+const IDLE_BUCKET: Duration = Duration::from_secs(600);
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Limit {
+    pub per_second: f64,
+    pub burst: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    pub read: Limit,
+    pub write: Limit,
+    /// Key anonymous clients on the right-most `X-Forwarded-For` hop instead of the peer address, behind a proxy that appends it.
+    pub trust_proxy: bool,
+}
+
+fn init_limit(errors: &mut ConfigErrors, class: &str, per_second: f64, burst: f64) -> Limit {
+    Limit {
+        per_second: errors.above::<f64>(&format!("RATE_LIMIT_{}_PER_SECOND", class), per_second, 0.0),
+        burst: errors.at_least::<f64>(&format!("RATE_LIMIT_{}_BURST", class), burst, 1.0),
+    }
+}
+
+/// Reads the `RATE_LIMIT_*` variables; `RATE_LIMIT_ENABLED=false` turns limiting off.
+pub fn init_rate_limit(errors: &mut ConfigErrors) -> Option<RateLimitConfig> {
+    match errors.parse::<bool>("RATE_LIMIT_ENABLED", true) {
+        false => None,
+        true => Some(RateLimitConfig {
+            read: init_limit(errors, "READ", 5.0, 20.0),
+            write: init_limit(errors, "WRITE", 0.5, 5.0),
+            trust_proxy: errors.parse::<bool>("RATE_LIMIT_TRUST_PROXY", false),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RouteClass {
+    Read,
+    Write
+}
+
+impl RouteClass {
+    fn from_scope(scope: Scope) -> Self {
+        match scope {
+            Scope::Mint | Scope::Transfer | Scope::Wrap => RouteClass::Write,
+            _ => RouteClass::Read
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match *self {
+            RouteClass::Read => "read",
+            RouteClass::Write => "write"
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn take(&mut self, limit: &Limit, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / limit.per_second))
+        }
+    }
+}
+
+/// Token buckets shared by every worker, keyed by client and route class.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<(String, RouteClass), Bucket>>,
+    rejected: IntCounterVec,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, registry: &Registry) -> Self {
+        let rejected = IntCounterVec::new(
+            Opts::new("rate_limited_requests_total", "Requests rejected by the rate limiter").namespace("api"),
+            &["class"]
+        ).unwrap();
+
+        registry.register(Box::new(rejected.clone())).unwrap();
+
+        RateLimiter {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            rejected,
+        }
+    }
+
+    fn check(&self, client: String, class: RouteClass) -> Result<(), ApiError> {
+        let limit = match class {
+            RouteClass::Read => &self.config.read,
+            RouteClass::Write => &self.config.write,
+        };
+
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let key = (client, class);
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&key) {
+            evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket { tokens: limit.burst, updated: now });
+
+        bucket.take(limit, now).map_err(|wait| {
+            self.rejected.with_label_values(&[class.as_str()]).inc();
+            ApiError::RateLimited(wait.as_secs_f64().ceil() as u64)
+        })
+    }
+}
+
+/// Drops idle buckets and, if that is not enough, the least recently used tenth, so that a flood of new clients
+/// costs one pass over the map per `MAX_BUCKETS / 10` of them.
+fn evict(buckets: &mut HashMap<(String, RouteClass), Bucket>, now: Instant) {
+    buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_BUCKET);
+
+    if buckets.len() >= MAX_BUCKETS {
+        let mut updated: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+        let (_, oldest, _) = updated.select_nth_unstable(MAX_BUCKETS / 10);
+        let cutoff = *oldest;
+        buckets.retain(|_, bucket| bucket.updated > cutoff);
+    }
+}
+
+/// The hop appended by the proxy in front of us; entries to its left come from the client and can be forged.
+fn forwarded_for(req: &ServiceRequest) -> Option<String> {
+    let header = req.headers().get("x-forwarded-for")?.to_str().ok()?;
+    header.rsplit(',').map(str::trim).find(|hop| !hop.is_empty()).map(str::to_string)
+}
+
+fn client_key(req: &ServiceRequest, trust_proxy: bool) -> String {
+    if let Some(caller) = req.extensions().get::<Caller>() {
+        return caller.id.to_owned();
+    }
+
+    let addr = match trust_proxy {
+        true => forwarded_for(req).or_else(|| req.peer_addr().map(|addr| addr.ip().to_string())),
+        false => req.peer_addr().map(|addr| addr.ip().to_string()),
+    };
+
+    format!("ip:{}", addr.unwrap_or_else(|| "unknown".to_string()))
+}
+
+/// Rate limits every route by the class of its scope. Routes registered with `write` count as writes whatever
+/// their scope, e.g. voucher redemption, which any read key may call but which sends a transaction.
+pub struct RateLimit {
+    limiter: Option<Arc<RateLimiter>>,
+    writes: Rc<Vec<&'static str>>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Option<Arc<RateLimiter>>) -> Self {
+        RateLimit { limiter, writes: Rc::new(Vec::new()) }
+    }
+
+    /// Counts the route registered under `name`, the handler name by default, as a write.
+    pub fn write(mut self, name: &'static str) -> Self {
+        Rc::make_mut(&mut self.writes).push(name);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+            writes: self.writes.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Option<Arc<RateLimiter>>,
+    writes: Rc<Vec<&'static str>>,
+}
+
+impl<S> RateLimitMiddleware<S> {
+    fn route_class(&self, req: &ServiceRequest) -> RouteClass {
+        match req.match_name() {
+            Some(name) if self.writes.contains(&name) => RouteClass::Write,
+            _ => RouteClass::from_scope(required_scope(req.method(), req.path()))
+        }
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(limiter) = &self.limiter {
+            if let Err(error) = limiter.check(client_key(&req, limiter.config.trust_proxy), self.route_class(&req)) {
+                let response = req.error_response(error).map_into_right_body();
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+
+        let service = self.service.clone();
+
+        Box::pin(async move { service.call(req).await.map(ServiceResponse::map_into_left_body) })
+    }
+}
+
+pub fn init_limiter(config: &Config, registry: &Registry) -> Option<Arc<RateLimiter>> {
+    config.rate_limit.clone().map(|rate_limit| Arc::new(RateLimiter::new(rate_limit, registry)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const LIMIT: Limit = Limit { per_second: 1.0, burst: 1.0 };
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig { read: LIMIT, write: LIMIT, trust_proxy: true }, &Registry::new())
+    }
+
+    #[test]
+    fn new_clients_never_grow_the_map_past_the_cap() {
+        let limiter = limiter();
+
+        for client in 0..MAX_BUCKETS + 50 {
+            limiter.check(format!("ip:{}", client), RouteClass::Read).unwrap();
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.len() <= MAX_BUCKETS);
+        assert!(buckets.contains_key(&(format!("ip:{}", MAX_BUCKETS + 49), RouteClass::Read)));
+    }
+
+    #[test]
+    fn only_the_right_most_forwarded_hop_is_trusted() {
+        let req = TestRequest::default().insert_header(("X-Forwarded-For", "1.2.3.4, 10.0.0.7")).to_srv_request();
+        assert_eq!(client_key(&req, true), "ip:10.0.0.7");
+
+        let req = TestRequest::default().insert_header(("X-Forwarded-For", "1.2.3.4, 10.0.0.7")).peer_addr("10.0.0.1:443".parse().unwrap()).to_srv_request();
+        assert_eq!(client_key(&req, false), "ip:10.0.0.1");
+    }
+
+    #[test]
+    fn callers_are_keyed_by_credential_not_name() {
+        let req = TestRequest::default().to_srv_request();
+        req.extensions_mut().insert(Caller { name: "shared".to_string(), id: "key:abc".to_string(), scopes: vec![], signers: vec![] });
+        assert_eq!(client_key(&req, false), "key:abc");
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{error::ApiError, config::{Config, ConfigErrors, non_empty}, chain::ChainConfig, extract::{ValidJson, validate_address}, stream::EventHub, auth::Caller, signer::{Signer, SignerRoute}, contracts::{asset_contract, forwarder_contract, MinimalForwarder}, asset::{AssetData, get_web3, get_asset_data, tx_response, TxResponse}, eip712::{Domain, type_hash, bytes_hash, struct_hash, typed_data_hash, parse_signature, recover_signer, parse_address, parse_u256, parse_bytes}};
+use ethcontract::{prelude::*, web3::ethabi::{decode, ParamType, Token}};
+use actix_web::{post, web::{Data, Json, ServiceConfig}, Responder};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use serde_json::{json, Value};
+use validator::Validate;
+
+const FORWARD_REQUEST_TYPE: &str = "ForwardRequest(address from,address to,uint256 value,uint256 gas,uint256 nonce,bytes data)";
+
+/// Selectors of `safeTransferFrom(address,address,uint256,uint256,bytes)` and
+/// `safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)`, the only calls the relay account pays for.
+const SAFE_TRANSFER_FROM: [u8; 4] = [0xf2, 0x42, 0x43, 0x2a];
+const SAFE_BATCH_TRANSFER_FROM: [u8; 4] = [0x2e, 0xb2, 0xc2, 0xd6];
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct MetaTransfer {
+    #[validate(custom = "validate_address")]
+    pub from: String,
+    #[validate(custom = "validate_address")]
+    pub to: String,
+    pub amount: u64,
+    pub id: u64,
+    #[validate]
+    pub data: AssetData,
+    pub gas: Option<u64>,
+    #[serde(default)]
+    pub chain: Option<String>,
+}
+
+/// The `ForwardRequest` message exactly as the wallet signed it.
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct ForwardRequestMessage {
+    #[validate(custom = "validate_address")]
+    pub from: String,
+    #[validate(custom = "validate_address")]
+    pub to: String,
+    pub value: String,
+    pub gas: String,
+    pub nonce: String,
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct SignedForwardRequest {
+    #[validate]
+    pub request: ForwardRequestMessage,
+    #[validate(length(min = 1))]
+    pub signature: String,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
+}
+
+struct ForwardRequest {
+    from: H160,
+    to: H160,
+    value: U256,
+    gas: U256,
+    nonce: U256,
+    data: Vec<u8>,
+}
+
+impl ForwardRequest {
+    fn parse(message: &ForwardRequestMessage) -> Result<Self, ApiError> {
+        Ok(ForwardRequest {
+            from: parse_address("from", &message.from)?,
+            to: parse_address("to", &message.to)?,
+            value: parse_u256("value", &message.value)?,
+            gas: parse_u256("gas", &message.gas)?,
+            nonce: parse_u256("nonce", &message.nonce)?,
+            data: parse_bytes("data", &message.data)?,
+        })
+    }
+
+    fn message(&self) -> ForwardRequestMessage {
+        ForwardRequestMessage {
+            from: format!("0x{:x}", self.from),
+            to: format!("0x{:x}", self.to),
+            value: self.value.to_string(),
+            gas: self.gas.to_string(),
+            nonce: self.nonce.to_string(),
+            data: format!("0x{}", hex::encode(&self.data)),
+        }
+    }
+
+    fn struct_hash(&self) -> [u8; 32] {
+        struct_hash(&[
+            type_hash(FORWARD_REQUEST_TYPE),
+            Token::Address(self.from),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::Uint(self.gas),
+            Token::Uint(self.nonce),
+            bytes_hash(&self.data),
+        ])
+    }
+
+    fn as_tuple(&self) -> (H160, H160, U256, U256, U256, Bytes<Vec<u8>>) {
+        (self.from, self.to, self.value, self.gas, self.nonce, Bytes(self.data.to_vec()))
+    }
+
+    /// Rejects calldata other than a transfer of `from`'s own tokens, before any gas is estimated or spent.
+    fn check_call(&self) -> Result<(), ApiError> {
+        let amounts = match self.data.get(..4) {
+            Some(selector) if selector == &SAFE_TRANSFER_FROM[..] => ParamType::Uint(256),
+            Some(selector) if selector == &SAFE_BATCH_TRANSFER_FROM[..] => ParamType::Array(Box::new(ParamType::Uint(256))),
+            _ => return Err(ApiError::InvalidRequest("only safeTransferFrom and safeBatchTransferFrom calls are relayed".to_string())),
+        };
+
+        match decode(&[ParamType::Address, ParamType::Address, amounts.clone(), amounts, ParamType::Bytes], &self.data[4..]) {
+            Ok(tokens) if tokens[0] == Token::Address(self.from) => Ok(()),
+            Ok(_) => Err(ApiError::InvalidRequest("relayed transfers can only move tokens of from".to_string())),
+            Err(_) => Err(ApiError::InvalidRequest("data is not a valid transfer call".to_string())),
+        }
+    }
+}
+
+/// A signed request that passed `relayer_verify`, with the chain and forwarder it was checked against.
+pub struct VerifiedRequest<'a> {
+    pub chain: &'a ChainConfig,
+    pub signer: H160,
+    web3: Web3<Http>,
+    forwarder: MinimalForwarder,
+    request: ForwardRequest,
+    signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RelayerConfig {
+    pub forwarder_name: String,
+    pub forwarder_version: String,
+    pub max_gas: u64,
+}
+
+pub fn init_relayer(errors: &mut ConfigErrors) -> RelayerConfig {
+    RelayerConfig {
+        forwarder_name: non_empty("FORWARDER_NAME").unwrap_or_else(|| "MinimalForwarder".to_string()),
+        forwarder_version: non_empty("FORWARDER_VERSION").unwrap_or_else(|| "0.0.1".to_string()),
+        max_gas: errors.parse::<u64>("RELAY_MAX_GAS", 500_000),
+    }
+}
+
+fn forwarder_domain(config: &Config, chain: &ChainConfig, forwarder: &MinimalForwarder) -> Domain {
+    Domain {
+        name: config.relayer.forwarder_name.to_owned(),
+        version: config.relayer.forwarder_version.to_owned(),
+        chain_id: chain.id,
+        verifying_contract: forwarder.address(),
+    }
+}
+
+fn typed_data(domain: &Domain, request: &ForwardRequest) -> Value {
+    json!({
+        "types": {
+            "EIP712Domain": Domain::type_json(),
+            "ForwardRequest": [
+                { "name": "from", "type": "address" },
+                { "name": "to", "type": "address" },
+                { "name": "value", "type": "uint256" },
+                { "name": "gas", "type": "uint256" },
+                { "name": "nonce", "type": "uint256" },
+                { "name": "data", "type": "bytes" }
+            ]
+        },
+        "domain": domain.to_json(),
+        "primaryType": "ForwardRequest",
+        "message": request.message()
+    })
+}
+
+/// Builds the `ForwardRequest` typed data a holder signs to move their own tokens.
+pub async fn relayer_build_transfer(config: &Config, transfer: &MetaTransfer) -> Result<Value, ApiError> {
+
+    let chain = config.chains.select(&transfer.chain)?;
+
+    let web3 = get_web3(chain)?;
+
+    let forwarder = forwarder_contract(chain, &web3).await?;
+
+    let contract = asset_contract(chain, &web3).await?;
+
+    let from = parse_address("from", &transfer.from)?;
+
+    let method = contract.safe_transfer_from(
+        from,
+        parse_address("to", &transfer.to)?,
+        transfer.id.into(),
+        transfer.amount.into(),
+        get_asset_data(transfer.data.name.to_owned(), transfer.data.symbol.to_owned(), transfer.data.decimals));
+
+    let request = ForwardRequest {
+        from,
+        to: contract.address(),
+        value: U256::zero(),
+        gas: transfer.gas.unwrap_or(config.relayer.max_gas).into(),
+        nonce: forwarder.get_nonce(from).call().await?,
+        data: method.tx.data.map(|data| data.0).unwrap_or_default(),
+    };
+
+    Ok(typed_data(&forwarder_domain(config, chain, &forwarder), &request))
+}
+
+/// Checks the signature and that the request is a transfer of the signer's tokens on the asset contract within the gas limit.
+pub async fn relayer_verify<'a>(config: &'a Config, signed: &SignedForwardRequest) -> Result<VerifiedRequest<'a>, ApiError> {
+
+    let chain = config.chains.select(&signed.chain)?;
+
+    let web3 = get_web3(chain)?;
+
+    let forwarder = forwarder_contract(chain, &web3).await?;
+
+    let contract = asset_contract(chain, &web3).await?;
+
+    let request = ForwardRequest::parse(&signed.request)?;
+
+    let signature = parse_signature(&signed.signature)?;
+
+    let recovered = recover_signer(typed_data_hash(&forwarder_domain(config, chain, &forwarder), request.struct_hash()), &signature)?;
+
+    if recovered != request.from {
+        return Err(ApiError::InvalidSignature(format!("signed by 0x{:x}, not by from", recovered)));
+    }
+    if request.to != contract.address() {
+        return Err(ApiError::InvalidRequest("only calls to the asset contract are relayed".to_string()));
+    }
+    if !request.value.is_zero() {
+        return Err(ApiError::InvalidRequest("relayed calls can't carry value".to_string()));
+    }
+    if request.gas > U256::from(config.relayer.max_gas) {
+        return Err(ApiError::InvalidRequest(format!("gas is above the relay limit of {}", config.relayer.max_gas)));
+    }
+
+    request.check_call()?;
+
+    if request.nonce != forwarder.get_nonce(request.from).call().await? {
+        return Err(ApiError::InvalidSignature("nonce was already used".to_string()));
+    }
+    if !forwarder.verify(request.as_tuple(), Bytes(signature.clone())).call().await? {
+        return Err(ApiError::InvalidSignature("rejected by the forwarder".to_string()));
+    }
+
+    Ok(VerifiedRequest { chain, signer: recovered, web3, forwarder, request, signature })
+}
+
+/// Verifies the signed request and submits it through the forwarder, paying the gas with the relay account.
+pub async fn relayer_relay(config: &Config, signer: &Signer, signed: &SignedForwardRequest) -> Result<H256, ApiError> {
+
+    let verified = relayer_verify(config, signed).await?;
+
+    let method = verified.forwarder.execute(verified.request.as_tuple(), Bytes(verified.signature));
+
+    signer.send(&verified.web3, verified.chain.id, method).await
+}
+
+async fn verify(signed: &SignedForwardRequest, config: &Config) -> Result<Json<Value>, ApiError> {
+    let verified = relayer_verify(config, signed).await?;
+
+    Ok(Json(json!({
+        "valid": true,
+        "signer": format!("0x{:x}", verified.signer)
+    })))
+}
+
+async fn relay(signed: &SignedForwardRequest, config: &Config, hub: &EventHub, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&signed.chain)?;
+    let signer = config.accounts.select(SignerRoute::Relay, &signed.signer, caller, chain)?;
+
+    let tx = relayer_relay(config, signer, signed).await?;
+
+    hub.track(tx, &chain.name, "relay_meta_transfer", [signed.request.from.to_owned()].to_vec(), [].to_vec());
+
+    Ok(tx_response(tx))
+}
+
+#[utoipa::path(
+    post,
+    path = "/build_meta_transfer",
+    tag = "legacy",
+    request_body = MetaTransfer,
+    responses(
+        (status = 200, description = "EIP-712 typed data for the forward request, to be signed by `from`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("build_meta_transfer")]
+async fn build_meta_transfer(req_data: ValidJson<MetaTransfer>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(relayer_build_transfer(&config, &req_data).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify_meta_transfer",
+    tag = "legacy",
+    request_body = SignedForwardRequest,
+    responses(
+        (status = 200, description = "Signature is valid, as `{\"valid\": true, \"signer\": address}`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("verify_meta_transfer")]
+async fn verify_meta_transfer(req_data: ValidJson<SignedForwardRequest>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    verify(&req_data, &config).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/relay_meta_transfer",
+    tag = "legacy",
+    request_body = SignedForwardRequest,
+    responses(
+        (status = 200, description = "Forwarder transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("relay_meta_transfer")]
+async fn relay_meta_transfer(req_data: ValidJson<SignedForwardRequest>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    relay(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/meta-transfers/build",
+    tag = "meta-transfers",
+    request_body = MetaTransfer,
+    responses(
+        (status = 200, description = "EIP-712 typed data for the forward request, to be signed by `from`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/meta-transfers/build")]
+async fn build_forward_request(req_data: ValidJson<MetaTransfer>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(Json(relayer_build_transfer(&config, &req_data).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/meta-transfers/verify",
+    tag = "meta-transfers",
+    request_body = SignedForwardRequest,
+    responses(
+        (status = 200, description = "Signature is valid, as `{\"valid\": true, \"signer\": address}`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/meta-transfers/verify")]
+async fn verify_forward_request(req_data: ValidJson<SignedForwardRequest>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    verify(&req_data, &config).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/meta-transfers/relay",
+    tag = "meta-transfers",
+    request_body = SignedForwardRequest,
+    responses(
+        (status = 200, description = "Forwarder transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/meta-transfers/relay")]
+async fn relay_forward_request(req_data: ValidJson<SignedForwardRequest>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    relay(&req_data, &config, &hub, &caller).await
+}
+
+/// Registers the legacy meta-transaction routes.
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(build_meta_transfer)
+        .service(verify_meta_transfer)
+        .service(relay_meta_transfer);
+}
+
+/// Registers the `/v1` meta-transaction routes, inside the `/v1` scope.
+pub fn configure_v1(cfg: &mut ServiceConfig) {
+    cfg.service(build_forward_request)
+        .service(verify_forward_request)
+        .service(relay_forward_request);
+}
@@ -0,0 +1,359 @@
+use crate::{error::ApiError, auth::Caller, config::{ConfigErrors, prefixed, non_empty}, chain::{ChainConfig, Chains}};
+use std::{collections::{HashMap, HashSet}, fs, sync::{Arc, Mutex}};
+use ethcontract::{prelude::*, tokens::Tokenize, web3::{signing::Key, types::{Bytes as RawBytes, CallRequest}, Transport}, BlockNumber};
+use serde_json::{json, Value};
+
+/// Signs and broadcasts contract transactions for the service account.
+#[derive(Clone, Debug)]
+pub enum Signer {
+    /// A key held in memory, read from `PRIVATE_KEY` or decrypted from a V3 keystore at startup.
+    Local(PrivateKey),
+    /// A Web3Signer-compatible JSON-RPC endpoint that answers `eth_signTransaction`.
+    Remote { url: String, address: H160 },
+}
+
+fn signer_error(error: impl std::fmt::Display) -> ApiError {
+    ApiError::SignerError(error.to_string())
+}
+
+fn read_keystore(path: &str, password_file: &str) -> Result<PrivateKey, String> {
+    let password = fs::read_to_string(password_file).map_err(|error| format!("{}: {}", password_file, error))?;
+    let key = eth_keystore::decrypt_key(path, password.trim_end_matches(&['\r', '\n'][..])).map_err(|error| format!("{}: {}", path, error))?;
+
+    PrivateKey::from_slice(&key).map_err(|error| format!("{}: {}", path, error))
+}
+
+/// Builds the signer selected by `SIGNER` (`private_key`, `keystore` or `remote`).
+/// Named accounts read the same variables with their upper-cased name as prefix, e.g. `MINTER_PRIVATE_KEY`.
+pub fn init_signer(prefix: &str) -> Result<Signer, String> {
+    let required = |name: &str| {
+        let name = prefixed(prefix, name);
+        non_empty(&name).ok_or_else(|| format!("{} is not set", name))
+    };
+
+    match non_empty(&prefixed(prefix, "SIGNER")).unwrap_or_else(|| "private_key".to_string()).as_str() {
+        "private_key" => required("PRIVATE_KEY")?
+            .parse()
+            .map(Signer::Local)
+            .map_err(|_| format!("{} is not a valid private key", prefixed(prefix, "PRIVATE_KEY"))),
+        "keystore" => read_keystore(&required("KEYSTORE_FILE")?, &required("KEYSTORE_PASSWORD_FILE")?)
+            .map(Signer::Local),
+        "remote" => Ok(Signer::Remote {
+            url: required("REMOTE_SIGNER_URL")?,
+            address: required("REMOTE_SIGNER_ADDRESS")?
+                .parse()
+                .map_err(|_| format!("{} is not a valid address", prefixed(prefix, "REMOTE_SIGNER_ADDRESS")))?,
+        }),
+        signer => Err(format!("unknown {} \"{}\"", prefixed(prefix, "SIGNER"), signer))
+    }
+}
+
+/// Chain ids listed in `SIGNER_CHAINS`, e.g. `SIGNER_CHAINS=1,137`, or `None` when the account may sign on every chain.
+fn init_signer_chains(prefix: &str) -> Result<Option<Vec<u64>>, String> {
+    let name = prefixed(prefix, "SIGNER_CHAINS");
+
+    let ids = non_empty(&name).unwrap_or_default()
+        .split(',')
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<u64>().map_err(|_| format!("{} has an invalid chain id \"{}\"", name, id)))
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    match ids.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(ids))
+    }
+}
+
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+#[derive(Clone, Copy, Debug)]
+pub enum SignerRoute {
+    Mint,
+    Transfer,
+    Wrap,
+    Relay,
+    Voucher
+}
+
+/// Named signing accounts and the account each write route uses unless the request picks another one.
+#[derive(Clone, Debug)]
+pub struct Accounts {
+    signers: HashMap<String, Signer>,
+    mint: String,
+    transfer: String,
+    wrap: String,
+    relay: String,
+    voucher: String,
+    /// Chain ids each account may sign on, from `SIGNER_CHAINS`; accounts without an entry sign on every chain.
+    chains: HashMap<String, Vec<u64>>,
+    low_balance: Arc<Mutex<HashSet<(u64, H160)>>>,
+    refuse_low_balance: bool,
+}
+
+/// Loads the default account (`SIGNER`/`PRIVATE_KEY`/...) and the named accounts listed in `SIGNER_ACCOUNTS`,
+/// recording the problems of every account. With `refuse_low_balance`, accounts flagged by the balance
+/// monitor can't be used for transactions.
+pub fn init_accounts(refuse_low_balance: bool, errors: &mut ConfigErrors) -> Accounts {
+    let names: Vec<String> = non_empty("SIGNER_ACCOUNTS").unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let mut signers: HashMap<String, Signer> = HashMap::new();
+    let mut chains: HashMap<String, Vec<u64>> = HashMap::new();
+
+    let mut accounts: Vec<(String, String)> = names.iter().map(|name| (name.to_owned(), name.to_uppercase())).collect();
+
+    if names.is_empty() || non_empty("SIGNER").is_some() || non_empty("PRIVATE_KEY").is_some() {
+        accounts.insert(0, (DEFAULT_ACCOUNT.to_string(), String::new()));
+    }
+
+    // An account that fails to load is still listed, so route settings naming it don't add a second error.
+    let mut listed: HashSet<String> = HashSet::new();
+
+    for (name, prefix) in accounts {
+        match init_signer(&prefix) {
+            Ok(signer) => { signers.insert(name.to_owned(), signer); },
+            Err(error) => errors.push(format!("invalid signer: {}", error)),
+        }
+        match init_signer_chains(&prefix) {
+            Ok(Some(ids)) => { chains.insert(name.to_owned(), ids); },
+            Ok(None) => (),
+            Err(error) => errors.push(format!("invalid signer: {}", error)),
+        }
+        listed.insert(name);
+    }
+
+    let mut route_account = |variable: &str| -> String {
+        let name = non_empty(variable).map(|name| name.to_lowercase()).unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+        if !listed.contains(&name) {
+            errors.push(format!("invalid signer: {} account \"{}\" is not configured", variable, name));
+        }
+        name
+    };
+
+    Accounts {
+        mint: route_account("MINT_SIGNER"),
+        transfer: route_account("TRANSFER_SIGNER"),
+        wrap: route_account("WRAP_SIGNER"),
+        relay: route_account("RELAY_SIGNER"),
+        voucher: route_account("VOUCHER_SIGNER"),
+        signers,
+        chains,
+        low_balance: Arc::new(Mutex::new(HashSet::new())),
+        refuse_low_balance,
+    }
+}
+
+impl Accounts {
+    pub fn route_account(&self, route: SignerRoute) -> &str {
+        match route {
+            SignerRoute::Mint => &self.mint,
+            SignerRoute::Transfer => &self.transfer,
+            SignerRoute::Wrap => &self.wrap,
+            SignerRoute::Relay => &self.relay,
+            SignerRoute::Voucher => &self.voucher,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Signer, ApiError> {
+        self.signers.get(name).ok_or_else(|| ApiError::UnknownSigner(name.to_string()))
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = (&String, &Signer)> {
+        self.signers.iter()
+    }
+
+    /// Whether `SIGNER_CHAINS` lets the account sign on the chain with id `chain_id`.
+    pub fn signs_on(&self, name: &str, chain_id: u64) -> bool {
+        self.chains.get(name).map_or(true, |ids| ids.contains(&chain_id))
+    }
+
+    /// The accounts that may sign on `chain`.
+    pub fn on_chain<'a>(&'a self, chain: &'a ChainConfig) -> impl Iterator<Item = (&'a String, &'a Signer)> {
+        self.signers.iter().filter(move |(name, _)| self.signs_on(name, chain.id))
+    }
+
+    /// Records whether an account is below the balance threshold on a chain, returning true when that changed.
+    pub fn set_low_balance(&self, chain_id: u64, address: H160, low: bool) -> bool {
+        let mut low_balance = self.low_balance.lock().unwrap();
+
+        match low {
+            true => low_balance.insert((chain_id, address)),
+            false => low_balance.remove(&(chain_id, address)),
+        }
+    }
+
+    /// Chain ids in `SIGNER_CHAINS` settings that no configured chain has, as configuration errors.
+    pub fn unknown_chains(&self, chains: &Chains) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        for (name, ids) in &self.chains {
+            let prefix = match name.as_str() {
+                DEFAULT_ACCOUNT => String::new(),
+                name => name.to_uppercase(),
+            };
+
+            for id in ids.iter().filter(|id| !chains.all().any(|chain| chain.id == **id)) {
+                errors.push(format!("{} lists chain {} which is not configured", prefixed(&prefix, "SIGNER_CHAINS"), id));
+            }
+        }
+
+        errors
+    }
+
+    /// Rejects a signer bound by `SIGNER_CHAINS` to other chains than the one the request is for.
+    pub fn check_chain(&self, name: &str, chain: &ChainConfig) -> Result<(), ApiError> {
+        match self.signs_on(name, chain.id) {
+            true => Ok(()),
+            false => Err(ApiError::SignerChainMismatch(name.to_string(), chain.name.to_owned()))
+        }
+    }
+
+    fn check_funds(&self, route: SignerRoute, signer: &Signer, chain: &ChainConfig) -> Result<(), ApiError> {
+        if let SignerRoute::Voucher = route {
+            return Ok(());
+        }
+
+        let address = signer.address();
+
+        match self.refuse_low_balance && self.low_balance.lock().unwrap().contains(&(chain.id, address)) {
+            true => Err(ApiError::InsufficientFunds(format!("0x{:x}", address))),
+            false => Ok(())
+        }
+    }
+
+    /// Picks the signer for a write on `chain`: the route account, or the requested one if the caller may use it.
+    pub fn select(&self, route: SignerRoute, requested: &Option<String>, caller: &Option<Caller>, chain: &ChainConfig) -> Result<&Signer, ApiError> {
+        let route_account = self.route_account(route);
+
+        let name = match requested {
+            Some(name) => name.to_lowercase(),
+            None => route_account.to_string(),
+        };
+
+        let signer = self.get(&name)?;
+
+        if name != route_account {
+            if let Some(caller) = caller {
+                if !caller.signers.contains(&name) {
+                    return Err(ApiError::SignerNotAllowed(name));
+                }
+            }
+        }
+
+        self.check_chain(&name, chain)?;
+        self.check_funds(route, signer, chain)?;
+
+        Ok(signer)
+    }
+}
+
+impl Signer {
+    pub fn address(&self) -> H160 {
+        match self {
+            Signer::Local(key) => key.public_address(),
+            Signer::Remote { address, .. } => *address,
+        }
+    }
+
+    pub fn account(&self, chain_id: u64) -> Account {
+        match self {
+            Signer::Local(key) => Account::Offline(key.to_owned(), Some(chain_id)),
+            Signer::Remote { address, .. } => Account::Local(*address, None),
+        }
+    }
+
+    /// Gas the method would use when sent from this signer.
+    pub async fn estimate_gas<R: Tokenize>(&self, web3: &Web3<Http>, method: &MethodBuilder<Http, R>) -> Result<U256, ApiError> {
+        let request = CallRequest {
+            from: Some(self.address()),
+            to: method.tx.to,
+            value: method.tx.value,
+            data: method.tx.data.clone(),
+            ..Default::default()
+        };
+
+        web3.eth().estimate_gas(request, None).await.map_err(signer_error)
+    }
+
+    /// Signs the contract call and sends it, returning the transaction hash.
+    pub async fn send<R: Tokenize>(&self, web3: &Web3<Http>, chain_id: u64, method: MethodBuilder<Http, R>) -> Result<H256, ApiError> {
+        match self {
+            Signer::Local(_) => {
+                let result = method.from(self.account(chain_id)).send().await?;
+
+                Ok(result.hash())
+            },
+            Signer::Remote { url, address } => {
+                let gas = match method.tx.gas {
+                    Some(gas) => gas,
+                    None => self.estimate_gas(web3, &method).await?,
+                };
+
+                let tx = method.tx;
+
+                let gas_price = web3.eth().gas_price().await.map_err(signer_error)?;
+                let nonce = match tx.nonce {
+                    Some(nonce) => nonce,
+                    None => web3.eth().transaction_count(*address, Some(BlockNumber::Pending)).await.map_err(signer_error)?,
+                };
+
+                let transaction = json!({
+                    "from": address,
+                    "to": tx.to,
+                    "gas": gas,
+                    "gasPrice": gas_price,
+                    "nonce": nonce,
+                    "value": tx.value.unwrap_or_default(),
+                    "data": tx.data.unwrap_or_default(),
+                    "chainId": format!("0x{:x}", chain_id)
+                });
+
+                let transport = Http::new(url).map_err(|_| ApiError::TransportError)?;
+
+                let signed = transport.execute("eth_signTransaction", vec![transaction]).await.map_err(signer_error)?;
+
+                let raw = match &signed {
+                    Value::String(raw) => raw.to_string(),
+                    Value::Object(object) => match object.get("raw") {
+                        Some(Value::String(raw)) => raw.to_string(),
+                        _ => return Err(signer_error("remote signer response has no raw transaction")),
+                    },
+                    _ => return Err(signer_error("unexpected remote signer response")),
+                };
+
+                let raw = hex::decode(raw.trim_start_matches("0x")).map_err(signer_error)?;
+
+                web3.eth().send_raw_transaction(RawBytes(raw)).await.map_err(signer_error)
+            }
+        }
+    }
+
+    /// Signs an EIP-712 digest; remote signers get the full typed data for `eth_signTypedData`.
+    pub async fn sign_typed_data(&self, digest: [u8; 32], typed_data: &Value) -> Result<Vec<u8>, ApiError> {
+        match self {
+            Signer::Local(key) => {
+                let signature = key.sign(&digest, None).map_err(signer_error)?;
+
+                let mut bytes = signature.r.as_bytes().to_vec();
+                bytes.extend_from_slice(signature.s.as_bytes());
+                bytes.push(signature.v as u8);
+
+                Ok(bytes)
+            },
+            Signer::Remote { url, address } => {
+                let transport = Http::new(url).map_err(|_| ApiError::TransportError)?;
+
+                let signed = transport.execute("eth_signTypedData", vec![json!(address), typed_data.clone()]).await.map_err(signer_error)?;
+
+                match signed {
+                    Value::String(signature) => hex::decode(signature.trim_start_matches("0x")).map_err(signer_error),
+                    _ => Err(signer_error("unexpected remote signer response")),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{error::ApiError, config::Config, events::{ContractEvent, EventFilter}};
+use std::{sync::Mutex, time::{Duration, Instant}};
+use ethcontract::H256;
+use actix_web::{get, web::{Bytes, Data, Query}, HttpResponse};
+use futures::{channel::mpsc::{unbounded, UnboundedSender}, StreamExt};
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxState {
+    Pending,
+    Mined,
+    Confirmed,
+    Failed,
+    Dropped
+}
+
+/// How long a transaction is followed before it is given up as dropped or replaced.
+const TX_MAX_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxStatus {
+    pub tx: H256,
+    pub chain: String,
+    pub action: String,
+    pub state: TxState,
+    pub block: Option<u64>,
+    pub addresses: Vec<String>,
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    pub chain: Option<String>,
+    pub contract: Option<String>,
+    pub address: Option<String>,
+    pub token_id: Option<u64>,
+}
+
+struct Subscriber {
+    filter: EventFilter,
+    sender: UnboundedSender<Bytes>,
+}
+
+struct TrackedTx {
+    status: TxStatus,
+    since: Instant,
+}
+
+#[derive(Default)]
+pub struct EventHub {
+    subscribers: Mutex<Vec<Subscriber>>,
+    transactions: Mutex<Vec<TrackedTx>>,
+}
+
+fn sse_message(name: &str, payload: &impl Serialize) -> Bytes {
+    Bytes::from(format!("event: {}\ndata: {}\n\n", name, serde_json::to_string(payload).unwrap()))
+}
+
+/// Transactions aren't tied to a contract, so a `contract` filter only narrows `contract_event` messages.
+fn matches_tx(filter: &EventFilter, status: &TxStatus) -> bool {
+    let chain = match &filter.chain {
+        Some(chain) => chain == &status.chain,
+        None => true,
+    };
+    let address = match &filter.address {
+        Some(address) => status.addresses.iter().any(|x| x.eq_ignore_ascii_case(address)),
+        None => true,
+    };
+    let token_id = match &filter.token_id {
+        Some(id) => status.ids.contains(&id.to_string()),
+        None => true,
+    };
+
+    chain && address && token_id
+}
+
+impl EventHub {
+    fn broadcast<F: Fn(&EventFilter) -> bool>(&self, message: Bytes, matches: F) {
+        self.subscribers.lock().unwrap().retain(|subscriber| {
+            if !matches(&subscriber.filter) {
+                return !subscriber.sender.is_closed();
+            }
+            subscriber.sender.unbounded_send(message.clone()).is_ok()
+        });
+    }
+
+    pub fn publish_event(&self, event: &ContractEvent) {
+        self.broadcast(sse_message("contract_event", event), |filter| filter.matches(event));
+    }
+
+    /// Starts following a transaction submitted by the service on `chain` until it is confirmed or fails.
+    pub fn track(&self, tx: H256, chain: &str, action: &str, addresses: Vec<String>, ids: Vec<u64>) {
+        let status = TxStatus {
+            tx,
+            chain: chain.to_string(),
+            action: action.to_string(),
+            state: TxState::Pending,
+            block: None,
+            addresses,
+            ids: ids.iter().map(|x| x.to_string()).collect(),
+        };
+
+        self.broadcast(sse_message("tx_status", &status), |filter| matches_tx(filter, &status));
+        self.transactions.lock().unwrap().push(TrackedTx { status, since: Instant::now() });
+    }
+
+    pub fn pending_transactions(&self, chain: &str) -> Vec<TxStatus> {
+        self.transactions.lock().unwrap().iter().filter(|tracked| tracked.status.chain == chain).map(|tracked| tracked.status.clone()).collect()
+    }
+
+    /// Stops following transactions on `chain` that weren't confirmed within `TX_MAX_AGE`, reporting them as dropped.
+    pub fn expire_transactions(&self, chain: &str) {
+        let expired: Vec<TxStatus> = {
+            let mut transactions = self.transactions.lock().unwrap();
+            let (expired, kept): (Vec<TrackedTx>, Vec<TrackedTx>) = transactions.drain(..).partition(|tracked| tracked.status.chain == chain && tracked.since.elapsed() > TX_MAX_AGE);
+            *transactions = kept;
+            expired.into_iter().map(|tracked| tracked.status).collect()
+        };
+
+        for mut status in expired {
+            status.state = TxState::Dropped;
+            self.broadcast(sse_message("tx_status", &status), |filter| matches_tx(filter, &status));
+        }
+    }
+
+    pub fn update_transaction(&self, status: TxStatus) {
+        {
+            let mut transactions = self.transactions.lock().unwrap();
+            match status.state {
+                TxState::Confirmed | TxState::Failed | TxState::Dropped => transactions.retain(|x| x.status.tx != status.tx || x.status.chain != status.chain),
+                _ => {
+                    for tracked in transactions.iter_mut().filter(|x| x.status.tx == status.tx && x.status.chain == status.chain) {
+                        tracked.status = status.clone();
+                    }
+                }
+            }
+        }
+
+        self.broadcast(sse_message("tx_status", &status), |filter| matches_tx(filter, &status));
+    }
+
+    pub fn keep_alive(&self) {
+        self.broadcast(Bytes::from_static(b": keep-alive\n\n"), |_| true);
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "events",
+    params(
+        StreamQuery
+    ),
+    responses(
+        (status = 200, description = "Server-sent `contract_event` and `tx_status` messages", content_type = "text/event-stream"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("events")]
+async fn event_stream(query: Query<StreamQuery>, hub: Data<EventHub>, config: Data<Config>) -> Result<HttpResponse, ApiError> {
+    let query = query.into_inner();
+
+    let chain = match &query.chain {
+        Some(_) => Some(config.chains.select(&query.chain)?.name.to_owned()),
+        None => None,
+    };
+
+    let filter = EventFilter {
+        chain,
+        contract: query.contract,
+        token_id: query.token_id,
+        address: query.address,
+        kinds: None,
+    };
+
+    let (sender, receiver) = unbounded();
+
+    hub.subscribers.lock().unwrap().push(Subscriber { filter, sender });
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/event-stream"))
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(receiver.map(Ok::<Bytes, ApiError>)))
+}
@@ -0,0 +1,432 @@
+use crate::{error::ApiError, config::{Config, ConfigErrors, non_empty}, chain::ChainConfig, extract::{ValidJson, validate_address}, stream::EventHub, auth::Caller, signer::{Signer, SignerRoute}, contracts::{asset_contract, SugarFungeAsset}, asset::{AssetData, AssetMint, get_web3, get_asset_data, asset_mint_nft, tx_response, TxResponse}, eip712::{Domain, type_hash, bytes_hash, struct_hash, typed_data_hash, parse_signature, recover_signer, parse_address, parse_u256}};
+use std::{collections::HashSet, fs, io::ErrorKind, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}}, time::{SystemTime, UNIX_EPOCH}};
+use ethcontract::{prelude::*, web3::ethabi::Token};
+use actix_web::{post, web::{block, Data, Json}, Responder};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use serde_json::{json, Value};
+use log::error;
+use validator::Validate;
+
+const MINT_VOUCHER_TYPE: &str = "MintVoucher(address recipient,uint256 id,uint256 amount,bytes data,uint256 expiry,uint256 nonce)";
+
+#[derive(Clone, Debug)]
+pub struct VoucherConfig {
+    pub domain_name: String,
+    pub domain_version: String,
+    pub ttl: u64,
+    pub nonces_file: Option<String>,
+}
+
+pub fn init_voucher(errors: &mut ConfigErrors) -> VoucherConfig {
+    VoucherConfig {
+        domain_name: non_empty("VOUCHER_DOMAIN_NAME").unwrap_or_else(|| "SugarFungeVoucher".to_string()),
+        domain_version: non_empty("VOUCHER_DOMAIN_VERSION").unwrap_or_else(|| "1".to_string()),
+        ttl: errors.parse::<u64>("VOUCHER_TTL", 86400),
+        nonces_file: non_empty("VOUCHER_NONCES_FILE"),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateMintVoucher {
+    #[validate(custom = "validate_address")]
+    pub recipient: String,
+    pub id: u64,
+    pub amount: u64,
+    #[validate]
+    pub data: AssetData,
+    pub expiry: Option<u64>,
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Only `VOUCHER_SIGNER` may be given: redemption accepts no other issuer.
+    #[serde(default)]
+    pub signer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate, ToSchema)]
+pub struct MintVoucher {
+    #[validate(custom = "validate_address")]
+    pub recipient: String,
+    pub id: u64,
+    pub amount: u64,
+    #[validate]
+    pub data: AssetData,
+    pub expiry: u64,
+    pub nonce: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct RedeemMintVoucher {
+    #[validate]
+    pub voucher: MintVoucher,
+    #[validate(length(min = 1))]
+    pub signature: String,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
+}
+
+/// Nonces of redeemed vouchers, written to `VOUCHER_NONCES_FILE` so they survive restarts. Nonces are kept
+/// in decimal, as the signature covers the number and not how the request spelled it.
+pub struct VoucherNonces {
+    used: Mutex<HashSet<String>>,
+    file: Option<String>,
+    /// Bumped on every change; the file is only replaced by a newer snapshot than the one it holds.
+    version: AtomicU64,
+    written: Arc<Mutex<u64>>,
+}
+
+impl VoucherNonces {
+    /// Reads `VOUCHER_NONCES_FILE`; a missing file starts empty, an unreadable or corrupt one is an error.
+    pub fn load(config: &Config) -> Result<Self, String> {
+        let used: HashSet<String> = match &config.voucher.nonces_file {
+            Some(file) => match fs::read_to_string(file) {
+                Ok(contents) => serde_json::from_str(&contents).map_err(|error| format!("VOUCHER_NONCES_FILE {} is invalid: {}", file, error))?,
+                Err(error) if error.kind() == ErrorKind::NotFound => HashSet::new(),
+                Err(error) => return Err(format!("reading VOUCHER_NONCES_FILE {} failed: {}", file, error)),
+            },
+            None => HashSet::new()
+        };
+
+        Ok(VoucherNonces {
+            used: Mutex::new(used.into_iter().map(|nonce| parse_u256("nonce", &nonce).map_or(nonce, |number| number.to_string())).collect()),
+            file: config.voucher.nonces_file.to_owned(),
+            version: AtomicU64::new(0),
+            written: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    fn snapshot(&self, used: &HashSet<String>) -> Option<(String, u64)> {
+        self.file.as_ref().map(|_| (serde_json::to_string(used).unwrap(), self.version.fetch_add(1, Ordering::SeqCst) + 1))
+    }
+
+    /// Writes the snapshot to a temporary file renamed over `VOUCHER_NONCES_FILE`, off the async runtime.
+    async fn save(&self, snapshot: Option<(String, u64)>) {
+        let (file, (contents, version)) = match (&self.file, snapshot) {
+            (Some(file), Some(snapshot)) => (file.to_owned(), snapshot),
+            _ => return,
+        };
+
+        let written = self.written.clone();
+        let path = file.clone();
+
+        let result = block(move || {
+            let mut written = written.lock().unwrap();
+            if version <= *written {
+                return Ok(());
+            }
+
+            let temporary = format!("{}.tmp", path);
+            fs::write(&temporary, contents).and_then(|_| fs::rename(&temporary, &path))?;
+
+            *written = version;
+            Ok::<(), std::io::Error>(())
+        }).await;
+
+        match result {
+            Ok(Ok(())) => (),
+            Ok(Err(error)) => error!("Saving voucher nonces to {} failed: {}", file, error),
+            Err(error) => error!("Saving voucher nonces to {} failed: {}", file, error),
+        }
+    }
+
+    /// Marks the voucher as redeemed, returning its nonce as stored for `release`.
+    async fn reserve(&self, voucher: &MintVoucher) -> Result<String, ApiError> {
+        let nonce = parse_u256("nonce", &voucher.nonce)?.to_string();
+
+        let snapshot = {
+            let mut used = self.used.lock().unwrap();
+
+            if !used.insert(nonce.to_owned()) {
+                return Err(ApiError::InvalidSignature("voucher was already redeemed".to_string()));
+            }
+
+            self.snapshot(&used)
+        };
+
+        self.save(snapshot).await;
+
+        Ok(nonce)
+    }
+
+    async fn release(&self, nonce: &str) {
+        let snapshot = {
+            let mut used = self.used.lock().unwrap();
+
+            used.remove(nonce);
+
+            self.snapshot(&used)
+        };
+
+        self.save(snapshot).await;
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn voucher_domain(config: &Config, chain: &ChainConfig, contract: &SugarFungeAsset) -> Domain {
+    Domain {
+        name: config.voucher.domain_name.to_owned(),
+        version: config.voucher.domain_version.to_owned(),
+        chain_id: chain.id,
+        verifying_contract: contract.address(),
+    }
+}
+
+fn voucher_hash(voucher: &MintVoucher) -> Result<[u8; 32], ApiError> {
+    let data = get_asset_data(voucher.data.name.to_owned(), voucher.data.symbol.to_owned(), voucher.data.decimals);
+
+    Ok(struct_hash(&[
+        type_hash(MINT_VOUCHER_TYPE),
+        Token::Address(parse_address("recipient", &voucher.recipient)?),
+        Token::Uint(voucher.id.into()),
+        Token::Uint(voucher.amount.into()),
+        bytes_hash(&data.0),
+        Token::Uint(voucher.expiry.into()),
+        Token::Uint(parse_u256("nonce", &voucher.nonce)?),
+    ]))
+}
+
+fn typed_data(domain: &Domain, voucher: &MintVoucher) -> Value {
+    let data = get_asset_data(voucher.data.name.to_owned(), voucher.data.symbol.to_owned(), voucher.data.decimals);
+
+    json!({
+        "types": {
+            "EIP712Domain": Domain::type_json(),
+            "MintVoucher": [
+                { "name": "recipient", "type": "address" },
+                { "name": "id", "type": "uint256" },
+                { "name": "amount", "type": "uint256" },
+                { "name": "data", "type": "bytes" },
+                { "name": "expiry", "type": "uint256" },
+                { "name": "nonce", "type": "uint256" }
+            ]
+        },
+        "domain": domain.to_json(),
+        "primaryType": "MintVoucher",
+        "message": {
+            "recipient": voucher.recipient,
+            "id": voucher.id.to_string(),
+            "amount": voucher.amount.to_string(),
+            "data": format!("0x{}", hex::encode(&data.0)),
+            "expiry": voucher.expiry.to_string(),
+            "nonce": voucher.nonce
+        }
+    })
+}
+
+/// Signs a voucher that lets `recipient` claim a mint later without the service paying gas up front.
+pub async fn voucher_create(config: &Config, signer: &Signer, create: &CreateMintVoucher) -> Result<Value, ApiError> {
+
+    let chain = config.chains.select(&create.chain)?;
+
+    let web3 = get_web3(chain)?;
+
+    let contract = asset_contract(chain, &web3).await?;
+
+    let voucher = MintVoucher {
+        recipient: format!("0x{:x}", parse_address("recipient", &create.recipient)?),
+        id: create.id,
+        amount: create.amount,
+        data: create.data.clone(),
+        expiry: create.expiry.unwrap_or_else(|| now() + config.voucher.ttl),
+        nonce: U256::from(rand::random::<u128>()).to_string(),
+    };
+
+    let domain = voucher_domain(config, chain, &contract);
+    let typed_data = typed_data(&domain, &voucher);
+
+    let signature = signer.sign_typed_data(typed_data_hash(&domain, voucher_hash(&voucher)?), &typed_data).await?;
+
+    Ok(json!({
+        "voucher": voucher,
+        "signature": format!("0x{}", hex::encode(signature)),
+        "typed_data": typed_data
+    }))
+}
+
+/// Checks the voucher against the voucher account and mints it, refusing expired or replayed vouchers.
+pub async fn voucher_redeem(config: &Config, signer: &Signer, nonces: &VoucherNonces, redeem: &RedeemMintVoucher) -> Result<H256, ApiError> {
+
+    let chain = config.chains.select(&redeem.chain)?;
+
+    let issuer = config.accounts.route_account(SignerRoute::Voucher);
+
+    config.accounts.check_chain(issuer, chain)?;
+
+    let web3 = get_web3(chain)?;
+
+    let contract = asset_contract(chain, &web3).await?;
+
+    let voucher = &redeem.voucher;
+
+    if voucher.expiry < now() {
+        return Err(ApiError::InvalidSignature("voucher has expired".to_string()));
+    }
+
+    let issuer = config.accounts.get(issuer)?.address();
+
+    let digest = typed_data_hash(&voucher_domain(config, chain, &contract), voucher_hash(voucher)?);
+
+    if recover_signer(digest, &parse_signature(&redeem.signature)?)? != issuer {
+        return Err(ApiError::InvalidSignature("voucher was not issued by this service".to_string()));
+    }
+
+    let nonce = nonces.reserve(voucher).await?;
+
+    let mint = AssetMint {
+        account: voucher.recipient.to_owned(),
+        amount: voucher.amount,
+        id: voucher.id,
+        data: voucher.data.clone(),
+        chain: redeem.chain.to_owned(),
+        signer: None,
+    };
+
+    match asset_mint_nft(config, signer, &mint).await {
+        Ok(tx) => Ok(tx),
+        Err(error) => {
+            nonces.release(&nonce).await;
+            Err(error)
+        }
+    }
+}
+
+async fn create(create: &CreateMintVoucher, config: &Config, caller: &Option<Caller>) -> Result<Json<Value>, ApiError> {
+    let issuer = config.accounts.route_account(SignerRoute::Voucher);
+
+    // Redemption only trusts the voucher account, so a voucher signed by any other could never be redeemed.
+    if let Some(signer) = &create.signer {
+        if !signer.eq_ignore_ascii_case(issuer) {
+            return Err(ApiError::InvalidRequest(format!("vouchers can only be signed by the VOUCHER_SIGNER account \"{}\"", issuer)));
+        }
+    }
+
+    let chain = config.chains.select(&create.chain)?;
+    let signer = config.accounts.select(SignerRoute::Voucher, &create.signer, caller, chain)?;
+
+    Ok(Json(voucher_create(config, signer, create).await?))
+}
+
+async fn redeem(redeem: &RedeemMintVoucher, config: &Config, hub: &EventHub, nonces: &VoucherNonces, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&redeem.chain)?;
+    let signer = config.accounts.select(SignerRoute::Mint, &redeem.signer, caller, chain)?;
+
+    let tx = voucher_redeem(config, signer, nonces, redeem).await?;
+
+    hub.track(tx, &chain.name, "redeem_mint_voucher", [redeem.voucher.recipient.to_owned()].to_vec(), [redeem.voucher.id].to_vec());
+
+    Ok(tx_response(tx))
+}
+
+#[utoipa::path(
+    post,
+    path = "/create_mint_voucher",
+    tag = "legacy",
+    request_body = CreateMintVoucher,
+    responses(
+        (status = 200, description = "Voucher with its `signature` and EIP-712 `typed_data`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("create_mint_voucher")]
+async fn create_mint_voucher(req_data: ValidJson<CreateMintVoucher>, config: Data<Config>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create(&req_data, &config, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/redeem_mint_voucher",
+    tag = "legacy",
+    request_body = RedeemMintVoucher,
+    responses(
+        (status = 200, description = "Mint transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("redeem_mint_voucher")]
+async fn redeem_mint_voucher(req_data: ValidJson<RedeemMintVoucher>, config: Data<Config>, hub: Data<EventHub>, nonces: Data<VoucherNonces>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    redeem(&req_data, &config, &hub, &nonces, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/vouchers",
+    tag = "vouchers",
+    request_body = CreateMintVoucher,
+    responses(
+        (status = 200, description = "Voucher with its `signature` and EIP-712 `typed_data`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/vouchers")]
+async fn create_voucher(req_data: ValidJson<CreateMintVoucher>, config: Data<Config>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    create(&req_data, &config, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/vouchers/redeem",
+    tag = "vouchers",
+    request_body = RedeemMintVoucher,
+    responses(
+        (status = 200, description = "Mint transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/vouchers/redeem")]
+async fn redeem_voucher(req_data: ValidJson<RedeemMintVoucher>, config: Data<Config>, hub: Data<EventHub>, nonces: Data<VoucherNonces>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    redeem(&req_data, &config, &hub, &nonces, &caller).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nonces() -> VoucherNonces {
+        VoucherNonces {
+            used: Mutex::new(HashSet::new()),
+            file: None,
+            version: AtomicU64::new(0),
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    fn voucher(nonce: &str) -> MintVoucher {
+        MintVoucher {
+            recipient: "0x0000000000000000000000000000000000000001".to_string(),
+            id: 1,
+            amount: 1,
+            data: AssetData { name: "Sugar".to_string(), symbol: "SGR".to_string(), decimals: 0 },
+            expiry: u64::MAX,
+            nonce: nonce.to_string(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_redeemed_nonce_spelled_in_hex() {
+        let nonces = nonces();
+
+        assert_eq!(nonces.reserve(&voucher("16")).await.unwrap(), "16");
+        assert!(matches!(nonces.reserve(&voucher("0x10")).await, Err(ApiError::InvalidSignature(_))));
+        assert!(matches!(nonces.reserve(&voucher("016")).await, Err(ApiError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn hex_and_decimal_nonces_sign_the_same_voucher() {
+        assert_eq!(voucher_hash(&voucher("16")).unwrap(), voucher_hash(&voucher("0x10")).unwrap());
+    }
+
+    #[actix_web::test]
+    async fn a_released_nonce_can_be_redeemed_again() {
+        let nonces = nonces();
+
+        let nonce = nonces.reserve(&voucher("0x10")).await.unwrap();
+        nonces.release(&nonce).await;
+
+        assert!(nonces.reserve(&voucher("16")).await.is_ok());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{error::ApiError, config::Config, extract::ValidJson, events::{ContractEvent, EventFilter}};
+use std::{net::{IpAddr, ToSocketAddrs}, sync::{Mutex, atomic::{AtomicU64, Ordering}}, time::Duration};
+use actix_web::{delete, get, post, rt::{spawn, time::sleep}, web::{block, Data, Json, Path}, Responder};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use serde_json::{json, Value};
+use log::warn;
+use validator::Validate;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "X-SugarFunge-Signature";
+
+/// Longest wait between two delivery attempts, in seconds.
+const MAX_BACKOFF: u64 = 300;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Webhook {
+    pub id: u64,
+    pub url: String,
+    pub filter: EventFilter,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct SubscribeWebhook {
+    #[validate(url)]
+    pub url: String,
+    #[serde(default)]
+    pub filter: EventFilter,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct WebhookId {
+    pub id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeadLetter {
+    pub webhook: Webhook,
+    pub event: ContractEvent,
+    pub attempts: u32,
+    pub error: String,
+}
+
+#[derive(Default)]
+pub struct WebhookStore {
+    next_id: AtomicU64,
+    webhooks: Mutex<Vec<Webhook>>,
+    dead_letters: Mutex<Vec<DeadLetter>>,
+}
+
+/// Whether `ip` is reachable only from inside the host or its network, so webhooks can't be aimed at it.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast()
+                || ip.is_multicast() || ip.is_documentation() || (a == 100 && (64..128).contains(&b)) || a == 0
+        },
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+            }
+        }
+    }
+}
+
+/// Host and port of an http(s) URL, without user info or brackets.
+fn url_host(url: &str) -> Option<(String, u16)> {
+    let (rest, port) = match url.split_once("://")? {
+        ("http", rest) => (rest, 80),
+        ("https", rest) => (rest, 443),
+        _ => return None,
+    };
+
+    let authority = rest.split(|c| c == '/' || c == '?' || c == '#').next()?;
+    let host = authority.rsplit('@').next()?;
+
+    let (host, port) = match host.strip_prefix('[') {
+        Some(host) => {
+            let (host, rest) = host.split_once(']')?;
+            (host, rest.strip_prefix(':').map_or(Some(port), |port| port.parse().ok())?)
+        },
+        None => match host.split_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (host, port),
+        }
+    };
+
+    match host.is_empty() {
+        true => None,
+        false => Some((host.to_lowercase(), port)),
+    }
+}
+
+/// Rejects webhook URLs that aren't http(s) or that resolve to loopback, link-local or private addresses.
+async fn check_url(url: &str) -> Result<(), String> {
+    let (host, port) = url_host(url).ok_or_else(|| "url must use http or https".to_string())?;
+
+    let addresses: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => [ip].to_vec(),
+        Err(_) => block(move || (host.as_str(), port).to_socket_addrs().map(|addresses| addresses.map(|address| address.ip()).collect::<Vec<IpAddr>>()))
+            .await
+            .map_err(|error| error.to_string())?
+            .map_err(|error| format!("url host doesn't resolve: {}", error))?,
+    };
+
+    match addresses.into_iter().any(is_internal) {
+        true => Err("url must not point to a loopback, link-local or private address".to_string()),
+        false => Ok(()),
+    }
+}
+
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+async fn post_webhook(url: &str, payload: &str, secret: &Option<String>) -> Result<(), String> {
+
+    // Checked again on every attempt, as the host may resolve elsewhere since it was subscribed.
+    check_url(url).await?;
+
+    let awc_client = awc::Client::new();
+
+    let mut request = awc_client.post(url)
+        .insert_header(("Content-Type", "application/json"));
+
+    if let Some(secret) = secret {
+        request = request.insert_header((SIGNATURE_HEADER, format!("sha256={}", sign_payload(secret, payload))));
+    }
+
+    match request.send_body(payload.to_owned()).await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!("Webhook responded with status {}", response.status())),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+async fn deliver(store: Data<WebhookStore>, webhook: Webhook, event: ContractEvent, secret: Option<String>, max_retries: u32) {
+
+    let payload = json!({
+        "webhook": webhook.id,
+        "event": event
+    }).to_string();
+
+    let mut attempts: u32 = 0;
+
+    loop {
+        attempts += 1;
+
+        let error = match post_webhook(&webhook.url, &payload, &secret).await {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+
+        if attempts > max_retries {
+            warn!("Webhook {} delivery to {} failed after {} attempts: {}", webhook.id, webhook.url, attempts, error);
+            store.dead_letters.lock().unwrap().push(DeadLetter { webhook, event, attempts, error });
+            return;
+        }
+
+        sleep(Duration::from_secs(2u64.saturating_pow(attempts).min(MAX_BACKOFF))).await;
+    }
+}
+
+/// Queues a delivery for every subscription whose filter matches the event.
+pub fn dispatch_event(store: &Data<WebhookStore>, config: &Config, event: &ContractEvent) {
+
+    let webhooks: Vec<Webhook> = store.webhooks.lock().unwrap()
+        .iter()
+        .filter(|webhook| webhook.filter.matches(event))
+        .cloned()
+        .collect();
+
+    for webhook in webhooks {
+        spawn(deliver(store.clone(), webhook, event.clone(), config.webhook_secret.to_owned(), config.webhook_max_retries));
+    }
+}
+
+/// Stores the subscription, with a `chain` filter given by id or Moralis slug kept as the chain's name.
+async fn subscribe(store: &WebhookStore, config: &Config, mut subscription: SubscribeWebhook) -> Result<Json<Webhook>, ApiError> {
+    check_url(&subscription.url).await.map_err(ApiError::InvalidWebhook)?;
+
+    if subscription.filter.chain.is_some() {
+        subscription.filter.chain = Some(config.chains.select(&subscription.filter.chain)?.name.to_owned());
+    }
+
+    let webhook = Webhook {
+        id: store.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+        url: subscription.url,
+        filter: subscription.filter,
+    };
+
+    store.webhooks.lock().unwrap().push(webhook.clone());
+
+    Ok(Json(webhook))
+}
+
+fn unsubscribe(store: &WebhookStore, id: u64) -> Result<Json<Webhook>, ApiError> {
+    let mut webhooks = store.webhooks.lock().unwrap();
+
+    match webhooks.iter().position(|webhook| webhook.id == id) {
+        Some(index) => Ok(Json(webhooks.remove(index))),
+        None => Err(ApiError::WebhookNotFound(id)),
+    }
+}
+
+fn retry(store: &Data<WebhookStore>, config: &Config) -> Json<Value> {
+    let dead_letters: Vec<DeadLetter> = store.dead_letters.lock().unwrap().drain(..).collect();
+
+    for dead_letter in &dead_letters {
+        spawn(deliver(store.clone(), dead_letter.webhook.clone(), dead_letter.event.clone(), config.webhook_secret.to_owned(), config.webhook_max_retries));
+    }
+
+    Json(json!({
+        "retried": dead_letters.len()
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/subscribe_webhook",
+    tag = "legacy",
+    request_body = SubscribeWebhook,
+    responses(
+        (status = 200, description = "Subscription created", body = Webhook),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("subscribe_webhook")]
+async fn subscribe_webhook(req_data: ValidJson<SubscribeWebhook>, store: Data<WebhookStore>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    subscribe(&store, &config, req_data.into_inner()).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/unsubscribe_webhook",
+    tag = "legacy",
+    request_body = WebhookId,
+    responses(
+        (status = 200, description = "Subscription removed", body = Webhook),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("unsubscribe_webhook")]
+async fn unsubscribe_webhook(req_data: ValidJson<WebhookId>, store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    unsubscribe(&store, req_data.id)
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_webhooks",
+    tag = "legacy",
+    responses(
+        (status = 200, description = "All subscriptions", body = [Webhook]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("get_webhooks")]
+async fn get_webhooks(store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    let webhooks = store.webhooks.lock().unwrap().to_vec();
+
+    Ok(Json(webhooks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_webhook_dead_letters",
+    tag = "legacy",
+    responses(
+        (status = 200, description = "Deliveries that ran out of retries", body = [DeadLetter]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("get_webhook_dead_letters")]
+async fn get_webhook_dead_letters(store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    let dead_letters = store.dead_letters.lock().unwrap().to_vec();
+
+    Ok(Json(dead_letters))
+}
+
+#[utoipa::path(
+    post,
+    path = "/retry_webhook_dead_letters",
+    tag = "legacy",
+    responses(
+        (status = 200, description = "Number of dead letters queued again, as `{\"retried\": n}`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("retry_webhook_dead_letters")]
+async fn retry_webhook_dead_letters(store: Data<WebhookStore>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(retry(&store, &config))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "All subscriptions", body = [Webhook]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/webhooks")]
+async fn list_webhooks(store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    let webhooks = store.webhooks.lock().unwrap().to_vec();
+
+    Ok(Json(webhooks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks",
+    tag = "webhooks",
+    request_body = SubscribeWebhook,
+    responses(
+        (status = 200, description = "Subscription created", body = Webhook),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/webhooks")]
+async fn create_webhook(req_data: ValidJson<SubscribeWebhook>, store: Data<WebhookStore>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    subscribe(&store, &config, req_data.into_inner()).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/webhooks/{id}",
+    tag = "webhooks",
+    params(
+        ("id" = u64, Path, description = "Subscription id")
+    ),
+    responses(
+        (status = 200, description = "Subscription removed", body = Webhook),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[delete("/webhooks/{id}")]
+async fn delete_webhook(id: Path<u64>, store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    unsubscribe(&store, id.into_inner())
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks/dead-letters",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Deliveries that ran out of retries", body = [DeadLetter]),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/webhooks/dead-letters")]
+async fn list_dead_letters(store: Data<WebhookStore>) -> Result<impl Responder, ApiError> {
+    let dead_letters = store.dead_letters.lock().unwrap().to_vec();
+
+    Ok(Json(dead_letters))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks/dead-letters/retry",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Number of dead letters queued again, as `{\"retried\": n}`"),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/webhooks/dead-letters/retry")]
+async fn retry_dead_letters(store: Data<WebhookStore>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    Ok(retry(&store, &config))
+}
@@ -1,157 +1,336 @@
-use crate::{error::ApiError, config::Config, asset::{AssetData, AssetTransfer, get_web3, get_asset_data, asset_transfer_nft, AssetBatchTransfer, asset_batch_transfer_nft}};
-use std::{env, str::FromStr, fmt::Debug};
+use crate::{error::ApiError, config::Config, chain::ChainQuery, contracts::{asset_contract, wrapper_factory_contract}, extract::{ValidJson, validate_address}, eip712::parse_address, batch::{BatchJobs, send_batch}, stream::EventHub, auth::Caller, signer::{Signer, SignerRoute}, asset::{AssetData, AssetTransfer, get_web3, get_asset_data, asset_transfer_nft, AssetBatchTransfer, tx_response, TxResponse}};
+use std::fmt::Debug;
 use ethcontract::prelude::*;
-use actix_web::{post, web::{Data, Json}, Responder};
+use actix_web::{get, post, web::{Data, Json, Path, Query, ServiceConfig}, HttpResponse, Responder};
 use serde::{Serialize, Deserialize};
-use serde_json::json;
+use utoipa::ToSchema;
+use validator::Validate;
 
-include!(concat!(env!("OUT_DIR"), "/SugarFungeAsset.rs"));
-include!(concat!(env!("OUT_DIR"), "/Wrapped1155Factory.rs"));
-
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct Wrap1155 {
-    from: String,
-    amount: u64,
-    id: u64,
-    data: AssetData,
+    #[validate(custom = "validate_address")]
+    pub from: String,
+    pub amount: u64,
+    pub id: u64,
+    #[validate]
+    pub data: AssetData,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct BatchWrap1155 {
-    from: String,
-    amounts: Vec<u64>,
-    ids: Vec<u64>,
+    #[validate(custom = "validate_address")]
+    pub from: String,
+    pub amounts: Vec<u64>,
+    #[validate(length(min = 1))]
+    pub ids: Vec<u64>,
+    #[validate]
     pub data: Vec<AssetData>,
+    #[serde(default)]
+    pub allow_duplicate_ids: bool,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct GetWrapped1155 {
-    id: u64,
-    data: AssetData,
+    pub id: u64,
+    #[validate]
+    pub data: AssetData,
+    #[serde(default)]
+    pub chain: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
 pub struct Unwrap1155 {
-    id: u64,
-    amount: u64,
-    recipient_address: String,
-    data: AssetData,
+    pub id: u64,
+    pub amount: u64,
+    #[validate(custom = "validate_address")]
+    pub recipient_address: String,
+    #[validate]
+    pub data: AssetData,
+    #[serde(default)]
+    pub chain: Option<String>,
+    #[serde(default)]
+    pub signer: Option<String>,
 }
 
-pub async fn wrapper_wrap(config: &Config, token: Wrap1155) -> Result<impl Responder, ApiError> {
+pub async fn wrapper_wrap(config: &Config, signer: &Signer, token: Wrap1155) -> Result<H256, ApiError> {
+
+    let chain = config.chains.select(&token.chain)?;
 
-    let web3 = get_web3(config)?;
+    let web3 = get_web3(chain)?;
 
-    let factory_contract = Wrapped1155Factory::deployed(&web3).await?;
+    let factory_contract = wrapper_factory_contract(chain, &web3).await?;
 
     let transfer = AssetTransfer {
         from: token.from,
-        to: format!("0x{:x}", Wrapped1155Factory::address(&factory_contract)),
+        to: format!("0x{:x}", factory_contract.address()),
         amount: token.amount,
         id: token.id,
-        data: token.data
+        data: token.data,
+        chain: token.chain,
+        signer: None
     };
 
-    asset_transfer_nft(config, &transfer).await
+    asset_transfer_nft(config, signer, &transfer).await
 }
 
-pub async fn wrapper_batch_wrap(config: &Config, token: BatchWrap1155) -> Result<impl Responder, ApiError> {
+/// The batch transfer into the wrapper factory that wraps `token`.
+pub async fn wrapper_batch_transfer(config: &Config, token: BatchWrap1155) -> Result<AssetBatchTransfer, ApiError> {
+
+    let chain = config.chains.select(&token.chain)?;
 
-    let web3 = get_web3(config)?;
+    let web3 = get_web3(chain)?;
 
-    let factory_contract = Wrapped1155Factory::deployed(&web3).await?;
+    let factory_contract = wrapper_factory_contract(chain, &web3).await?;
 
-    let transfer = AssetBatchTransfer {
+    Ok(AssetBatchTransfer {
         from: token.from,
-        to: format!("0x{:x}", Wrapped1155Factory::address(&factory_contract)),
+        to: format!("0x{:x}", factory_contract.address()),
         amounts: token.amounts,
         ids: token.ids,
-        data: token.data
-    };
-
-    asset_batch_transfer_nft(config, &transfer).await
+        data: token.data,
+        allow_duplicate_ids: token.allow_duplicate_ids,
+        chain: token.chain,
+        signer: None
+    })
 }
 
-pub async fn wrapper_unwrap(config: &Config, unwrap: &Unwrap1155) -> Result<impl Responder, ApiError> {
-
-    let account = {
-        let key: PrivateKey = config.private_key.to_owned();
-        Account::Offline(key, Some(config.chain_id))
-    };
+pub async fn wrapper_unwrap(config: &Config, signer: &Signer, unwrap: &Unwrap1155) -> Result<H256, ApiError> {
 
-    let web3 = get_web3(config)?;
+    let chain = config.chains.select(&unwrap.chain)?;
 
-    let mut contract = Wrapped1155Factory::deployed(&web3).await?;
+    let web3 = get_web3(chain)?;
 
-    contract.defaults_mut().from = Some(account);
+    let contract = wrapper_factory_contract(chain, &web3).await?;
 
-    let sugarfunge_contract = SugarFungeAsset::deployed(&web3).await?;
+    let sugarfunge_contract = asset_contract(chain, &web3).await?;
 
-    let result = contract.unwrap(
-        H160::from_str(&format!("0x{:x}", SugarFungeAsset::address(&sugarfunge_contract))).unwrap(),
+    let method = contract.unwrap(
+        sugarfunge_contract.address(),
         unwrap.id.into(), 
         unwrap.amount.into(), 
-        H160::from_str(&unwrap.recipient_address).unwrap(), 
-        get_asset_data(unwrap.data.name.to_owned(), unwrap.data.symbol.to_owned(), unwrap.data.decimals))
-        .send()
-        .await?;
+        parse_address("recipient_address", &unwrap.recipient_address)?, 
+        get_asset_data(unwrap.data.name.to_owned(), unwrap.data.symbol.to_owned(), unwrap.data.decimals));
 
-    Ok(Json(json!({
-        "tx": format!("0x{:x}", result.hash())
-    })))
+    signer.send(&web3, chain.id, method).await
 }
 
-pub async fn wrapper_get_wrapped(config: &Config, wrapped: &GetWrapped1155) -> Result<impl Responder, ApiError> {
-
-    let account = {
-        let key: PrivateKey = config.private_key.to_owned();
-        Account::Offline(key, Some(config.chain_id))
-    };
+pub async fn wrapper_get_wrapped(config: &Config, wrapped: &GetWrapped1155) -> Result<H160, ApiError> {
 
-    let web3 = get_web3(config)?;
+    let chain = config.chains.select(&wrapped.chain)?;
 
-    let mut contract = Wrapped1155Factory::deployed(&web3).await?;
+    let web3 = get_web3(chain)?;
 
-    contract.defaults_mut().from = Some(account);
+    let contract = wrapper_factory_contract(chain, &web3).await?;
 
-    let sugarfunge_contract = SugarFungeAsset::deployed(&web3).await?;
+    let sugarfunge_contract = asset_contract(chain, &web3).await?;
 
     let result = contract.get_wrapped_1155(
-        H160::from_str(&format!("0x{:x}", SugarFungeAsset::address(&sugarfunge_contract))).unwrap(), 
+        sugarfunge_contract.address(), 
         wrapped.id.into(), 
         get_asset_data(wrapped.data.name.to_owned(), wrapped.data.symbol.to_owned(), wrapped.data.decimals))
         .call()
         .await?;
 
-    Ok(Json(json!({
-        "tx": format!("0x{:x}", result)
-    })))
+    Ok(result)
 }
 
-#[post("wrap_1155")]
-async fn wrap_1155(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Wrap1155 = serde_json::from_str(&req_body)?;
+/// The wrapped token address, answered in the `tx` field like the original route.
+async fn get_wrapped(wrapped: &GetWrapped1155, config: &Config) -> Result<Json<TxResponse>, ApiError> {
+    let address = wrapper_get_wrapped(config, wrapped).await?;
 
-    wrapper_wrap(&config, req_data).await
+    Ok(Json(TxResponse {
+        tx: format!("0x{:x}", address)
+    }))
 }
 
-#[post("batch_wrap_1155")]
-async fn batch_wrap_1155(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: BatchWrap1155 = serde_json::from_str(&req_body)?;
+async fn send_wrap(token: Wrap1155, config: &Config, hub: &EventHub, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&token.chain)?;
+    let signer = config.accounts.select(SignerRoute::Wrap, &token.signer, caller, chain)?;
 
-    wrapper_batch_wrap(&config, req_data).await
+    let addresses = [token.from.to_owned()].to_vec();
+    let ids = [token.id].to_vec();
+
+    let tx = wrapper_wrap(config, signer, token).await?;
+
+    hub.track(tx, &chain.name, "wrap_1155", addresses, ids);
+
+    Ok(tx_response(tx))
 }
 
-#[post("unwrap_1155")]
-async fn unwrap_1155(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: Unwrap1155 = serde_json::from_str(&req_body)?;
+async fn send_batch_wrap(token: BatchWrap1155, config: &Data<Config>, hub: &Data<EventHub>, jobs: &Data<BatchJobs>, caller: &Option<Caller>) -> Result<HttpResponse, ApiError> {
+    let chain = config.chains.select(&token.chain)?;
+    let signer = config.accounts.select(SignerRoute::Wrap, &token.signer, caller, chain)?;
+
+    let transfer = wrapper_batch_transfer(config, token).await?;
 
-    wrapper_unwrap(&config, &req_data).await
+    send_batch(config, jobs, hub, chain, signer, transfer, "batch_wrap_1155").await
 }
 
+async fn send_unwrap(unwrap: &Unwrap1155, config: &Config, hub: &EventHub, caller: &Option<Caller>) -> Result<Json<TxResponse>, ApiError> {
+    let chain = config.chains.select(&unwrap.chain)?;
+    let signer = config.accounts.select(SignerRoute::Wrap, &unwrap.signer, caller, chain)?;
+
+    let tx = wrapper_unwrap(config, signer, unwrap).await?;
+
+    hub.track(tx, &chain.name, "unwrap_1155", [unwrap.recipient_address.to_owned()].to_vec(), [unwrap.id].to_vec());
+
+    Ok(tx_response(tx))
+}
+
+#[utoipa::path(
+    post,
+    path = "/wrap_1155",
+    tag = "legacy",
+    request_body = Wrap1155,
+    responses(
+        (status = 200, description = "Wrap transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("wrap_1155")]
+async fn wrap_1155(req_data: ValidJson<Wrap1155>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_wrap(req_data.into_inner(), &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/batch_wrap_1155",
+    tag = "legacy",
+    request_body = BatchWrap1155,
+    responses(
+        (status = 200, description = "Sent as one transaction", body = TxResponse),
+        (status = 202, description = "Too large for one transaction, sent as a chunked job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("batch_wrap_1155")]
+async fn batch_wrap_1155(req_data: ValidJson<BatchWrap1155>, config: Data<Config>, hub: Data<EventHub>, jobs: Data<BatchJobs>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_batch_wrap(req_data.into_inner(), &config, &hub, &jobs, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/unwrap_1155",
+    tag = "legacy",
+    request_body = Unwrap1155,
+    responses(
+        (status = 200, description = "Unwrap transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("unwrap_1155")]
+async fn unwrap_1155(req_data: ValidJson<Unwrap1155>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_unwrap(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/get_wrapped_1155",
+    tag = "legacy",
+    request_body = GetWrapped1155,
+    responses(
+        (status = 200, description = "Address of the wrapped token, in the `tx` field", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
 #[post("get_wrapped_1155")]
-async fn get_wrapped_1155(req_body: String, config: Data<Config>) -> Result<impl Responder, ApiError> {
-    let req_data: GetWrapped1155 = serde_json::from_str(&req_body)?;
+async fn get_wrapped_1155(req_data: ValidJson<GetWrapped1155>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    get_wrapped(&req_data, &config).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/wraps",
+    tag = "wraps",
+    request_body = Wrap1155,
+    responses(
+        (status = 200, description = "Wrap transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/wraps")]
+async fn wrap_token(req_data: ValidJson<Wrap1155>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_wrap(req_data.into_inner(), &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/wraps/batch",
+    tag = "wraps",
+    request_body = BatchWrap1155,
+    responses(
+        (status = 200, description = "Sent as one transaction", body = TxResponse),
+        (status = 202, description = "Too large for one transaction, sent as a chunked job", body = BatchJob),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/wraps/batch")]
+async fn batch_wrap_tokens(req_data: ValidJson<BatchWrap1155>, config: Data<Config>, hub: Data<EventHub>, jobs: Data<BatchJobs>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_batch_wrap(req_data.into_inner(), &config, &hub, &jobs, &caller).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/unwraps",
+    tag = "wraps",
+    request_body = Unwrap1155,
+    responses(
+        (status = 200, description = "Unwrap transaction submitted", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[post("/unwraps")]
+async fn unwrap_token(req_data: ValidJson<Unwrap1155>, config: Data<Config>, hub: Data<EventHub>, caller: Option<Caller>) -> Result<impl Responder, ApiError> {
+    send_unwrap(&req_data, &config, &hub, &caller).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/wrapped/{id}",
+    tag = "wraps",
+    params(
+        ("id" = u64, Path, description = "Token id"),
+        AssetData,
+        ChainQuery
+    ),
+    responses(
+        (status = 200, description = "Address of the wrapped token, in the `tx` field", body = TxResponse),
+        (status = "default", description = "Error", body = crate::error::ErrorResponse)
+    )
+)]
+#[get("/wrapped/{id}")]
+async fn get_wrapped_token(id: Path<u64>, data: Query<AssetData>, chain: Query<ChainQuery>, config: Data<Config>) -> Result<impl Responder, ApiError> {
+    let wrapped = GetWrapped1155 {
+        id: id.into_inner(),
+        data: data.into_inner(),
+        chain: chain.into_inner().chain,
+    };
+
+    wrapped.validate().map_err(ApiError::ValidationError)?;
+
+    get_wrapped(&wrapped, &config).await
+}
+
+/// Registers the legacy wrapper routes.
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(wrap_1155)
+        .service(batch_wrap_1155)
+        .service(unwrap_1155)
+        .service(get_wrapped_1155);
+}
 
-    wrapper_get_wrapped(&config, &req_data).await
+/// Registers the `/v1` wrapper routes, inside the `/v1` scope.
+pub fn configure_v1(cfg: &mut ServiceConfig) {
+    cfg.service(wrap_token)
+        .service(batch_wrap_tokens)
+        .service(unwrap_token)
+        .service(get_wrapped_token);
 }
@@ -0,0 +1,129 @@
+//! Bearer token checks against keys generated locally with:
+//!
+//! ```bash
+//! $ openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out es256.pem
+//! $ openssl pkey -in es256.pem -pubout -out es256.pub.pem
+//! $ openssl genpkey -algorithm RSA -pkeyopt rsa_keygen_bits:2048 -out rs256.pem
+//! ```
+//!
+//! `jwks.json` holds the public half of `rs256.pem` under the kid `test-rsa`.
+
+use std::{env, time::{SystemTime, UNIX_EPOCH}};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+use sugarfunge_integration::{auth::Scope, config::ConfigErrors, error::ApiError, jwt::{init_jwt, JwtConfig, JwtVerifier}};
+
+const ES256_KEY: &str = include_str!("fixtures/jwt/es256.pem");
+const ES256_PUBLIC_KEY: &str = include_str!("fixtures/jwt/es256.pub.pem");
+const RS256_KEY: &str = include_str!("fixtures/jwt/rs256.pem");
+const JWKS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwt/jwks.json");
+
+fn config() -> JwtConfig {
+    JwtConfig {
+        hs256_secret: None,
+        public_key: None,
+        jwks: None,
+        jwks_cache_secs: 300,
+        audience: Some("sugarfunge".to_string()),
+        issuer: None,
+        scope_claim: "scope".to_string(),
+    }
+}
+
+fn pem_verifier() -> JwtVerifier {
+    JwtVerifier::new(JwtConfig { public_key: Some(ES256_PUBLIC_KEY.to_string()), ..config() })
+}
+
+fn jwks_verifier() -> JwtVerifier {
+    JwtVerifier::new(JwtConfig { jwks: Some(JWKS.to_string()), ..config() })
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn claims(audience: &str, expires: u64) -> Value {
+    json!({
+        "sub": "game-server",
+        "aud": audience,
+        "exp": expires,
+        "scope": "read mint unknown"
+    })
+}
+
+fn es256_token(claims: &Value) -> String {
+    encode(&Header::new(Algorithm::ES256), claims, &EncodingKey::from_ec_pem(ES256_KEY.as_bytes()).unwrap()).unwrap()
+}
+
+fn rs256_token(kid: &str, claims: &Value) -> String {
+    let header = Header { kid: Some(kid.to_string()), ..Header::new(Algorithm::RS256) };
+
+    encode(&header, claims, &EncodingKey::from_rsa_pem(RS256_KEY.as_bytes()).unwrap()).unwrap()
+}
+
+#[actix_web::test]
+async fn accepts_a_valid_token() {
+    let caller = pem_verifier().verify(&es256_token(&claims("sugarfunge", now() + 600))).await.unwrap();
+
+    assert_eq!(caller.name, "game-server");
+    assert_eq!(caller.scopes, [Scope::Read, Scope::Mint].to_vec());
+}
+
+#[actix_web::test]
+async fn accepts_a_valid_jwks_token() {
+    let caller = jwks_verifier().verify(&rs256_token("test-rsa", &claims("sugarfunge", now() + 600))).await.unwrap();
+
+    assert_eq!(caller.scopes, [Scope::Read, Scope::Mint].to_vec());
+}
+
+#[actix_web::test]
+async fn rejects_an_expired_token() {
+    let result = pem_verifier().verify(&es256_token(&claims("sugarfunge", now() - 600))).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(reason)) if reason.contains("Expired")));
+}
+
+#[actix_web::test]
+async fn rejects_a_wrong_audience() {
+    let result = pem_verifier().verify(&es256_token(&claims("another-api", now() + 600))).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(reason)) if reason.contains("Audience")));
+}
+
+#[actix_web::test]
+async fn rejects_an_unknown_kid() {
+    let result = jwks_verifier().verify(&rs256_token("rotated-away", &claims("sugarfunge", now() + 600))).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(reason)) if reason == "unknown kid \"rotated-away\""));
+}
+
+#[actix_web::test]
+async fn rejects_a_token_signed_by_another_key() {
+    let verifier = JwtVerifier::new(JwtConfig { public_key: Some(ES256_PUBLIC_KEY.to_string()), ..config() });
+
+    let result = verifier.verify(&rs256_token("test-rsa", &claims("sugarfunge", now() + 600))).await;
+
+    assert!(matches!(result, Err(ApiError::InvalidToken(_))));
+}
+
+// The only test here that reads `JWT_*` variables, as the environment is shared by the test threads.
+#[test]
+fn empty_settings_leave_jwt_disabled() {
+    for name in ["JWT_HS256_SECRET", "JWT_PUBLIC_KEY_FILE", "JWT_JWKS", "JWT_AUDIENCE", "JWT_ISSUER"] {
+        env::set_var(name, "");
+    }
+
+    let mut errors = ConfigErrors::default();
+
+    assert!(init_jwt(&mut errors).is_none());
+    assert!(errors.into_result(|| ()).is_ok());
+
+    env::set_var("JWT_HS256_SECRET", "short");
+
+    let mut errors = ConfigErrors::default();
+    init_jwt(&mut errors);
+
+    assert_eq!(errors.into_result(|| ()).unwrap_err(), ["JWT_HS256_SECRET must be at least 32 bytes"]);
+
+    env::set_var("JWT_HS256_SECRET", "");
+}